@@ -0,0 +1,71 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   arena.rs
+//
+
+// Run with `cargo bench --features arena`, parses a generated document with a `Box` per node
+// and again out of an `AstArena`, printing the fastest and average time of each.
+
+use std::time::Duration;
+use std::time::Instant;
+
+const SECTION_COUNT: usize = 10_000;
+const ITERATION_COUNT: u32 = 20;
+
+fn generate_document() -> String {
+    let mut source = String::new();
+
+    for i in 0..SECTION_COUNT {
+        source.push_str(&format!(
+            "@h2(ID=\"section-{i}\"){{ Section {i} }}\n\
+             @text{{ Some @b{{bold}} and @i{{italic}} text with a @link(Src=\"#section-{i}\"){{link}}. }}\n\
+             @ulist{{ @listitem{{One}} @listitem{{Two @code{{value {i}}}}} @listitem{{Three}} }}\n\
+             @image(Src=\"image-{i}.png\", Width=640, Height=480, Alt=\"Image {i}\")\n\n",
+        ));
+    }
+
+    return source;
+}
+
+fn time<F: FnMut()>(name: &str, mut parse: F) {
+    let mut fastest = Duration::MAX;
+    let mut total = Duration::ZERO;
+
+    for _ in 0..ITERATION_COUNT {
+        let start = Instant::now();
+        parse();
+        let elapsed = start.elapsed();
+
+        fastest = fastest.min(elapsed);
+        total += elapsed;
+    }
+
+    println!(
+        "{:<6} fastest {:>8.2?}  average {:>8.2?}",
+        name,
+        fastest,
+        total / ITERATION_COUNT
+    );
+}
+
+fn main() {
+    let source = generate_document();
+
+    println!(
+        "Parsing {} KiB ({} sections), {} iterations each",
+        source.len() / 1024,
+        SECTION_COUNT,
+        ITERATION_COUNT
+    );
+
+    time("box", || {
+        let root = srmarkup::Parser::new(source.clone()).parse();
+        assert!(root.is_ok());
+    });
+
+    time("arena", || {
+        let arena = srmarkup::AstArena::new();
+        let root = srmarkup::Parser::new(source.clone()).parse_in_arena(&arena);
+        assert!(root.is_ok());
+    });
+}
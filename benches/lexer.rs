@@ -0,0 +1,82 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   lexer.rs
+//
+
+// Run with `cargo bench --bench lexer`, lexes generated documents of doubling size and prints the time
+// per KiB, which stays flat now that `Lexer::char_at` decodes a single char at a byte offset.
+// The `chars().nth()` rows time the previous way of reading each char (walking the string from the
+// start every time) over the same documents, that time per KiB doubles along with the size.
+
+use std::time::Duration;
+use std::time::Instant;
+
+const SECTION_COUNTS: [usize; 4] = [250, 500, 1_000, 2_000];
+const NTH_SECTION_LIMIT: usize = 500; // The quadratic scan takes too long past this.
+const ITERATION_COUNT: u32 = 10;
+
+fn generate_document(section_count: usize) -> String {
+    let mut source = String::new();
+
+    for i in 0..section_count {
+        source.push_str(&format!(
+            "@h2(ID=\"section-{i}\"){{ Section {i}, café }}\n\
+             @text{{ Some @b{{bold}} and @i{{italic}} text with a @link(Src=\"#section-{i}\"){{link}}. }}\n\
+             @ulist{{ @listitem{{One}} @listitem{{Two {i}.5}} @listitem{{true}} }}\n\n",
+        ));
+    }
+
+    return source;
+}
+
+fn fastest<F: FnMut()>(mut run: F) -> Duration {
+    let mut fastest = Duration::MAX;
+
+    for _ in 0..ITERATION_COUNT {
+        let start = Instant::now();
+        run();
+        fastest = fastest.min(start.elapsed());
+    }
+
+    return fastest;
+}
+
+fn print_row(name: &str, source: &str, elapsed: Duration) {
+    let kib = source.len() as f64 / 1024.0;
+
+    println!(
+        "{:<14} {:>7.1} KiB  fastest {:>10.2?}  {:>10.2?} / KiB",
+        name,
+        kib,
+        elapsed,
+        elapsed.div_f64(kib)
+    );
+}
+
+fn main() {
+    for section_count in SECTION_COUNTS {
+        let source = generate_document(section_count);
+
+        let elapsed = fastest(|| {
+            let token_count = srmarkup::Lexer::new(source.clone()).count();
+            assert!(token_count > section_count);
+        });
+        print_row("lexer", &source, elapsed);
+
+        if section_count <= NTH_SECTION_LIMIT {
+            let char_count = source.chars().count();
+            let elapsed = fastest(|| {
+                let mut at_count = 0;
+
+                for index in 0..char_count {
+                    if source.chars().nth(index) == Some('@') {
+                        at_count += 1;
+                    }
+                }
+
+                assert!(at_count > section_count);
+            });
+            print_row("chars().nth()", &source, elapsed);
+        }
+    }
+}
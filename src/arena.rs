@@ -0,0 +1,198 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   arena.rs
+//
+
+//! Prototype of building the tree out of an arena rather than a `Box` per node (`arena` feature).
+//!
+//! The nodes live in an `AstArena` owned by the caller and the tree borrows from it, children are
+//! still kept in a `Vec` per tag. The processors only work on `ASTNode`s, `ArenaNode::to_ast_node`
+//! converts a tree for them. `cargo bench --features arena` compares parsing both ways.
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast::AttributeMap;
+use crate::ast::Span;
+use crate::parser::ParseErrors;
+use crate::parser::Parser;
+use crate::parser::TreeBuilder;
+
+/// Storage for every node of a tree parsed by `Parser::parse_in_arena`, freed all at once when dropped.
+pub type AstArena<'a> = typed_arena::Arena<ArenaNode<'a>>;
+
+pub type ArenaNodeList<'a> = Vec<&'a ArenaNode<'a>>;
+
+/// Same shape as `ASTNode`, with the children borrowed from an `AstArena`.
+#[derive(Debug)]
+pub enum ArenaNode<'a> {
+    Root {
+        children: ArenaNodeList<'a>,
+        span: Span,
+    },
+    Tag {
+        text: String,
+        attributes: AttributeMap,
+        children: ArenaNodeList<'a>,
+        span: Span,
+    },
+    Text(ASTNodeText),
+    Literal(ASTNodeLiteral),
+}
+
+impl ArenaNode<'_> {
+    /// Copies the tree into `Box`ed nodes, e.g. to run an `IASTProcessor` over it.
+    pub fn to_ast_node(self: &Self) -> ASTNode {
+        let to_ast_list = |children: &ArenaNodeList| {
+            children
+                .iter()
+                .map(|child| Box::new(child.to_ast_node()))
+                .collect()
+        };
+
+        return match self {
+            ArenaNode::Root { children, span } => ASTNode::Root(ASTNodeRoot {
+                children: to_ast_list(children),
+                span: *span,
+            }),
+            ArenaNode::Tag {
+                text,
+                attributes,
+                children,
+                span,
+            } => ASTNode::Tag(ASTNodeTag {
+                text: text.clone(),
+                children: to_ast_list(children),
+                attributes: attributes.clone(),
+                span: *span,
+            }),
+            ArenaNode::Text(text_node) => ASTNode::Text(text_node.clone()),
+            ArenaNode::Literal(literal_node) => ASTNode::Literal(literal_node.clone()),
+        };
+    }
+}
+
+struct ArenaTreeBuilder<'a> {
+    arena: &'a AstArena<'a>,
+}
+
+impl<'a> TreeBuilder for ArenaTreeBuilder<'a> {
+    type Node = &'a ArenaNode<'a>;
+
+    fn root(&mut self, root: ASTNodeRoot, children: ArenaNodeList<'a>) -> Self::Node {
+        return self.arena.alloc(ArenaNode::Root {
+            children,
+            span: root.span,
+        });
+    }
+
+    fn tag(&mut self, tag: ASTNodeTag, children: ArenaNodeList<'a>) -> Self::Node {
+        return self.arena.alloc(ArenaNode::Tag {
+            text: tag.text,
+            attributes: tag.attributes,
+            children,
+            span: tag.span,
+        });
+    }
+
+    fn text(&mut self, text: ASTNodeText) -> Self::Node {
+        return self.arena.alloc(ArenaNode::Text(text));
+    }
+
+    fn literal(&mut self, literal: ASTNodeLiteral) -> Self::Node {
+        return self.arena.alloc(ArenaNode::Literal(literal));
+    }
+}
+
+impl Parser {
+    /// Same as `parse` but every node is allocated from `arena`.
+    /// There is no `ParseErrors::partial_root` for an arena tree, the errors are all that is returned.
+    ///
+    /// ```
+    /// let arena = srmarkup::AstArena::new();
+    /// let root = srmarkup::Parser::new("@p{Hello}".to_string()).parse_in_arena(&arena).ok().unwrap();
+    ///
+    /// assert_eq!(root.to_ast_node(), *srmarkup::parse("@p{Hello}").ok().unwrap());
+    /// ```
+    pub fn parse_in_arena<'a>(
+        &mut self,
+        arena: &'a AstArena<'a>,
+    ) -> Result<&'a ArenaNode<'a>, ParseErrors> {
+        let (root_node, error_log) = self.parse_tree(&mut ArenaTreeBuilder { arena });
+
+        return if error_log.is_empty() {
+            Ok(root_node)
+        } else {
+            Err(ParseErrors {
+                errors: error_log,
+                partial_root: None,
+            })
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_spans(node: &ASTNode, spans: &mut Vec<Span>) {
+        let children = match node {
+            ASTNode::Root(root_node) => {
+                spans.push(root_node.span);
+                &root_node.children
+            }
+            ASTNode::Tag(tag_node) => {
+                spans.push(tag_node.span);
+                &tag_node.children
+            }
+            ASTNode::Text(text_node) => {
+                spans.push(text_node.span);
+                return;
+            }
+            ASTNode::Literal(_) => return,
+        };
+
+        for child in children {
+            collect_spans(child, spans);
+        }
+    }
+
+    #[test]
+    fn arena_tree_matches_the_boxed_tree() {
+        let source = std::fs::read_to_string("TestInput.srmark").unwrap();
+        let arena = AstArena::new();
+
+        let boxed_root = Parser::new(source.clone()).parse().ok().unwrap();
+        let arena_root = Parser::new(source)
+            .parse_in_arena(&arena)
+            .ok()
+            .unwrap()
+            .to_ast_node();
+
+        // NOTE(SR): `==` ignores spans so they are compared on their own.
+        let mut boxed_spans = Vec::new();
+        let mut arena_spans = Vec::new();
+        collect_spans(&boxed_root, &mut boxed_spans);
+        collect_spans(&arena_root, &mut arena_spans);
+
+        assert_eq!(arena_root, *boxed_root);
+        assert_eq!(arena_spans, boxed_spans);
+    }
+
+    #[test]
+    fn arena_parse_reports_the_same_errors() {
+        let arena = AstArena::new();
+        let errors = Parser::new("@p(class){x}".to_string())
+            .parse_in_arena(&arena)
+            .err()
+            .unwrap();
+        let boxed_errors = crate::parse("@p(class){x}").err().unwrap();
+
+        assert_eq!(
+            errors.render("@p(class){x}"),
+            boxed_errors.render("@p(class){x}")
+        );
+    }
+}
@@ -3,6 +3,9 @@
 // File:   ast.rs
 //
 
+use crate::ast_events::events;
+use crate::ast_events::AstEvent;
+
 use std::collections::HashMap;
 
 // AST Nodes
@@ -10,45 +13,387 @@ use std::collections::HashMap;
 pub type ASTNodePtr = Box<ASTNode>;
 pub type ASTNodeList = Vec<ASTNodePtr>;
 
+/// The (1 based, inclusive) source lines a node was parsed from, along with where its first character is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub start_column: usize, // 1 based, counted in characters.
+    pub start_offset: usize, // Byte offset into the source.
+}
+
+impl Span {
+    /// Lines `start_line..=end_line`, starting at the beginning of `start_line`'s first column.
+    pub fn new(start_line: usize, end_line: usize) -> Self {
+        return Span {
+            start_line,
+            end_line,
+            start_column: 1,
+            start_offset: 0,
+        };
+    }
+
+    /// A node starting at `line` / `column` (`offset` bytes into the source), ending on the same line.
+    pub fn at(line: usize, column: usize, offset: usize) -> Self {
+        return Span {
+            start_line: line,
+            end_line: line,
+            start_column: column,
+            start_offset: offset,
+        };
+    }
+}
+
+// NOTE(SR): With the `serde` feature enums are written as `{"type": "tag", "value": {...}}`
+//           and attributes as a plain object.
+
 /// A parsed document will have exactly one root ast node.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ASTNodeRoot {
     pub children: ASTNodeList,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+impl ASTNodeRoot {
+    /// Every tag named `name` (compared ignoring ASCII case) anywhere in the document, in document order.
+    ///
+    /// ```
+    /// let root = srmarkup::parse("@Image(Src=\"a\") @p{ @image(Src=\"b\") }").ok().unwrap();
+    /// let srmarkup::ASTNode::Root(root) = root.as_ref() else { unreachable!() };
+    ///
+    /// let sources: Vec<_> = root.find_tags("image").filter_map(|tag| tag.find_attribute_str("Src")).collect();
+    /// assert_eq!(sources, ["a", "b"]);
+    /// assert!(root.find_first_tag("header").is_none());
+    /// ```
+    pub fn find_tags<'a>(
+        self: &'a Self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a ASTNodeTag> + 'a {
+        return self
+            .children
+            .iter()
+            .flat_map(|child| events(child))
+            .filter_map(move |event| match event {
+                AstEvent::EnterTag(tag_node) if tag_node.text.eq_ignore_ascii_case(name) => {
+                    Some(tag_node)
+                }
+                _ => None,
+            });
+    }
+
+    /// The first tag `find_tags` would return.
+    pub fn find_first_tag(self: &Self, name: &str) -> Option<&ASTNodeTag> {
+        for child in &self.children {
+            for event in events(child) {
+                if let AstEvent::EnterTag(tag_node) = event {
+                    if tag_node.text.eq_ignore_ascii_case(name) {
+                        return Some(tag_node);
+                    }
+                }
+            }
+        }
+
+        return None;
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum ASTNodeLiteral {
     Str(String),
-    Float(f64),
+    Float {
+        value: f64,
+        raw: Option<String>, // Source text, only kept when the parser was asked to preserve it.
+    },
     Bool(bool),
+    Measure {
+        value: f64,
+        unit: String, // `%`, `px`, `fr`, ...
+    },
+    List(Vec<ASTNodeLiteral>),
 }
 
-impl ToString for ASTNodeLiteral {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for ASTNodeLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ASTNodeLiteral::Str(value) => value.clone(),
-            ASTNodeLiteral::Float(value) => value.to_string(),
-            ASTNodeLiteral::Bool(value) => value.to_string(),
+            ASTNodeLiteral::Str(value) => write!(f, "{}", value),
+            ASTNodeLiteral::Float { raw: Some(raw), .. } => write!(f, "{}", raw),
+            ASTNodeLiteral::Float { value, raw: None } => write!(f, "{}", value),
+            ASTNodeLiteral::Bool(value) => write!(f, "{}", value),
+            ASTNodeLiteral::Measure { value, unit } => write!(f, "{}{}", value, unit),
+            ASTNodeLiteral::List(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    match item {
+                        ASTNodeLiteral::Str(value) => write!(f, "{:?}", value)?,
+                        _ => write!(f, "{}", item)?,
+                    }
+                }
+                write!(f, "]")
+            }
         }
     }
 }
 
+/// The attributes of a tag in the order they were written, with hash map lookup by name.
+#[derive(Clone, Debug, Default)]
+pub struct AttributeMap {
+    entries: Vec<(String, ASTNodeLiteral)>,
+    indices: HashMap<String, usize>, // Name to its index in `entries`.
+}
+
+impl AttributeMap {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    pub fn len(self: &Self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    pub fn get(self: &Self, key: &str) -> Option<&ASTNodeLiteral> {
+        return self.indices.get(key).map(|index| &self.entries[*index].1);
+    }
+
+    pub fn get_mut(self: &mut Self, key: &str) -> Option<&mut ASTNodeLiteral> {
+        return match self.indices.get(key) {
+            Some(index) => Some(&mut self.entries[*index].1),
+            None => None,
+        };
+    }
+
+    pub fn contains_key(self: &Self, key: &str) -> bool {
+        return self.indices.contains_key(key);
+    }
+
+    /// Setting an existing attribute again replaces its value but keeps its original position,
+    /// the old value is returned.
+    pub fn insert(self: &mut Self, key: String, value: ASTNodeLiteral) -> Option<ASTNodeLiteral> {
+        if let Some(index) = self.indices.get(&key) {
+            return Some(std::mem::replace(&mut self.entries[*index].1, value));
+        }
+
+        self.indices.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+
+        return None;
+    }
+
+    pub fn remove(self: &mut Self, key: &str) -> Option<ASTNodeLiteral> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+
+        for later_index in self.indices.values_mut() {
+            if *later_index > index {
+                *later_index -= 1;
+            }
+        }
+
+        return Some(value);
+    }
+
+    pub fn iter(self: &Self) -> impl Iterator<Item = (&String, &ASTNodeLiteral)> {
+        return self.entries.iter().map(|(key, value)| (key, value));
+    }
+
+    pub fn keys(self: &Self) -> impl Iterator<Item = &String> {
+        return self.entries.iter().map(|(key, _)| key);
+    }
+
+    pub fn values(self: &Self) -> impl Iterator<Item = &ASTNodeLiteral> {
+        return self.entries.iter().map(|(_, value)| value);
+    }
+
+    pub fn values_mut(self: &mut Self) -> impl Iterator<Item = &mut ASTNodeLiteral> {
+        return self.entries.iter_mut().map(|(_, value)| value);
+    }
+}
+
+impl<'a> IntoIterator for &'a AttributeMap {
+    type Item = (&'a String, &'a ASTNodeLiteral);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, ASTNodeLiteral)>,
+        fn(&'a (String, ASTNodeLiteral)) -> (&'a String, &'a ASTNodeLiteral),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        return self.entries.iter().map(|(key, value)| (key, value));
+    }
+}
+
+impl FromIterator<(String, ASTNodeLiteral)> for AttributeMap {
+    fn from_iter<I: IntoIterator<Item = (String, ASTNodeLiteral)>>(iter: I) -> Self {
+        let mut result = AttributeMap::new();
+
+        for (key, value) in iter {
+            result.insert(key, value);
+        }
+
+        return result;
+    }
+}
+
+impl std::ops::Index<&str> for AttributeMap {
+    type Output = ASTNodeLiteral;
+
+    fn index(&self, key: &str) -> &ASTNodeLiteral {
+        return self.get(key).expect("No attribute with that name.");
+    }
+}
+
+// NOTE(SR): Written as a json object (like the `HashMap` it replaced) with the keys in order.
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AttributeMap {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.collect_map(self.iter());
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for AttributeMap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AttributeMapVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for AttributeMapVisitor {
+            type Value = AttributeMap;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map of attribute names to literals")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<AttributeMap, A::Error> {
+                let mut result = AttributeMap::new();
+
+                while let Some((key, value)) = access.next_entry::<String, ASTNodeLiteral>()? {
+                    result.insert(key, value);
+                }
+
+                return Ok(result);
+            }
+        }
+
+        return deserializer.deserialize_map(AttributeMapVisitor);
+    }
+}
+
 /// main building block for the document, can be nested and have key value pair of extra metadata.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ASTNodeTag {
     pub text: String,
     pub children: ASTNodeList,
-    pub attributes: HashMap<String, ASTNodeLiteral>,
+    pub attributes: AttributeMap, // In the order they were written.
+    pub span: Span, // From the tag's name up to its closing '}' (or ')' if it has no body).
 }
 
 impl ASTNodeTag {
     pub fn find_attribute(self: &Self, key: &str) -> Option<&ASTNodeLiteral> {
         return self.attributes.get(key);
     }
+
+    /// Like `find_attribute` but `src`, `SRC` and `Src` all match a `Src` attribute.
+    /// An exact match wins, otherwise the first matching key in the order they were written is used.
+    pub fn find_attribute_ci(self: &Self, key: &str) -> Option<&ASTNodeLiteral> {
+        if let Some(value) = self.attributes.get(key) {
+            return Some(value);
+        }
+
+        return self
+            .attributes
+            .iter()
+            .find(|(attribute_key, _)| attribute_key.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value);
+    }
+
+    /// `None` if the attribute is missing or not a string.
+    pub fn find_attribute_str(self: &Self, key: &str) -> Option<&str> {
+        return match self.find_attribute(key) {
+            Some(ASTNodeLiteral::Str(value)) => Some(value.as_str()),
+            _ => None,
+        };
+    }
+
+    /// `None` if the attribute is missing or not a number.
+    pub fn find_attribute_f64(self: &Self, key: &str) -> Option<f64> {
+        return self.find_attribute(key).and_then(f64::from_literal);
+    }
+
+    /// `None` if the attribute is missing or not a boolean.
+    pub fn find_attribute_bool(self: &Self, key: &str) -> Option<bool> {
+        return self.find_attribute(key).and_then(bool::from_literal);
+    }
+
+    /// The attribute as a `T`, or `default` if it is missing or of another type.
+    pub fn find_attribute_or<T: FromLiteral>(self: &Self, key: &str, default: T) -> T {
+        return self
+            .find_attribute(key)
+            .and_then(T::from_literal)
+            .unwrap_or(default);
+    }
+}
+
+/// Types an attribute value can be read back as (see `ASTNodeTag::find_attribute_or`).
+pub trait FromLiteral: Sized {
+    fn from_literal(literal: &ASTNodeLiteral) -> Option<Self>;
+}
+
+impl FromLiteral for String {
+    fn from_literal(literal: &ASTNodeLiteral) -> Option<Self> {
+        return match literal {
+            ASTNodeLiteral::Str(value) => Some(value.clone()),
+            _ => None,
+        };
+    }
 }
 
+impl FromLiteral for f64 {
+    fn from_literal(literal: &ASTNodeLiteral) -> Option<Self> {
+        return match literal {
+            ASTNodeLiteral::Float { value, .. } => Some(*value),
+            _ => None,
+        };
+    }
+}
+
+impl FromLiteral for bool {
+    fn from_literal(literal: &ASTNodeLiteral) -> Option<Self> {
+        return match literal {
+            ASTNodeLiteral::Bool(value) => Some(*value),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ASTNodeText {
     pub text: String,
+    pub span: Span,
 }
 
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value", rename_all = "snake_case")
+)]
 pub enum ASTNode {
     Root(ASTNodeRoot),
     Tag(ASTNodeTag),
@@ -62,6 +407,240 @@ impl ASTNodeTag {
             text,
             children: Default::default(),
             attributes: Default::default(),
+            span: Default::default(),
+        }
+    }
+}
+
+impl ASTNodeText {
+    pub fn new(text: String, span: Span) -> Self {
+        Self { text, span }
+    }
+}
+
+// AST Comparison
+
+/// Structural comparison of two trees: tag names, attributes, text and literals.
+/// Where things were written (spans, the source text of numbers) is not compared,
+/// so differently formatted documents with the same content are equal.
+pub fn ast_eq_ignoring_spans(lhs: &ASTNode, rhs: &ASTNode) -> bool {
+    return match (lhs, rhs) {
+        (ASTNode::Root(lhs), ASTNode::Root(rhs)) => {
+            ast_list_eq_ignoring_spans(&lhs.children, &rhs.children)
+        }
+        (ASTNode::Tag(lhs), ASTNode::Tag(rhs)) => tag_eq_ignoring_spans(lhs, rhs),
+        (ASTNode::Text(lhs), ASTNode::Text(rhs)) => lhs.text == rhs.text,
+        (ASTNode::Literal(lhs), ASTNode::Literal(rhs)) => literal_eq_ignoring_spans(lhs, rhs),
+        _ => false,
+    };
+}
+
+// NOTE(SR): `==` is the same structural comparison so tests can `assert_eq!` a hand built tree
+//           against a parsed one without having to fill in spans.
+
+impl PartialEq for ASTNode {
+    fn eq(&self, other: &Self) -> bool {
+        return ast_eq_ignoring_spans(self, other);
+    }
+}
+
+impl PartialEq for ASTNodeRoot {
+    fn eq(&self, other: &Self) -> bool {
+        return ast_list_eq_ignoring_spans(&self.children, &other.children);
+    }
+}
+
+impl PartialEq for ASTNodeTag {
+    fn eq(&self, other: &Self) -> bool {
+        return tag_eq_ignoring_spans(self, other);
+    }
+}
+
+impl PartialEq for ASTNodeText {
+    fn eq(&self, other: &Self) -> bool {
+        return self.text == other.text;
+    }
+}
+
+impl PartialEq for ASTNodeLiteral {
+    fn eq(&self, other: &Self) -> bool {
+        return literal_eq_ignoring_spans(self, other);
+    }
+}
+
+fn tag_eq_ignoring_spans(lhs: &ASTNodeTag, rhs: &ASTNodeTag) -> bool {
+    return lhs.text == rhs.text
+        && lhs.attributes.len() == rhs.attributes.len()
+        && lhs.attributes.iter().all(|(key, value)| {
+            rhs.attributes
+                .get(key)
+                .is_some_and(|rhs_value| literal_eq_ignoring_spans(value, rhs_value))
+        })
+        && ast_list_eq_ignoring_spans(&lhs.children, &rhs.children);
+}
+
+fn ast_list_eq_ignoring_spans(lhs: &ASTNodeList, rhs: &ASTNodeList) -> bool {
+    return lhs.len() == rhs.len()
+        && lhs
+            .iter()
+            .zip(rhs.iter())
+            .all(|(lhs, rhs)| ast_eq_ignoring_spans(lhs, rhs));
+}
+
+fn literal_eq_ignoring_spans(lhs: &ASTNodeLiteral, rhs: &ASTNodeLiteral) -> bool {
+    return match (lhs, rhs) {
+        (ASTNodeLiteral::Str(lhs), ASTNodeLiteral::Str(rhs)) => lhs == rhs,
+        (ASTNodeLiteral::Float { value: lhs, .. }, ASTNodeLiteral::Float { value: rhs, .. }) => {
+            lhs == rhs
+        }
+        (ASTNodeLiteral::Bool(lhs), ASTNodeLiteral::Bool(rhs)) => lhs == rhs,
+        (
+            ASTNodeLiteral::Measure {
+                value: lhs_value,
+                unit: lhs_unit,
+            },
+            ASTNodeLiteral::Measure {
+                value: rhs_value,
+                unit: rhs_unit,
+            },
+        ) => lhs_value == rhs_value && lhs_unit == rhs_unit,
+        (ASTNodeLiteral::List(lhs), ASTNodeLiteral::List(rhs)) => {
+            lhs.len() == rhs.len()
+                && lhs
+                    .iter()
+                    .zip(rhs.iter())
+                    .all(|(lhs, rhs)| literal_eq_ignoring_spans(lhs, rhs))
         }
+        _ => false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn parse_node(source: &str) -> ASTNode {
+        return *parse(source).ok().unwrap();
+    }
+
+    #[test]
+    fn documents_that_differ_only_in_layout_are_equal() {
+        let compact = parse_node("@div(Class=\"a\" Width=2.0){@b{bold} text}");
+        let spread = parse_node(
+            "\n\n  @div( Class = \"a\"\n        Width = 2 )\n  {\n    @b{bold} text\n  }",
+        );
+
+        assert!(ast_eq_ignoring_spans(&compact, &spread));
+        assert!(ast_eq_ignoring_spans(&spread, &compact));
+    }
+
+    #[test]
+    fn different_content_is_not_equal() {
+        let document = parse_node("@div(Class=\"a\"){@b{bold} text}");
+
+        for other in [
+            "@span(Class=\"a\"){@b{bold} text}",
+            "@div(Class=\"b\"){@b{bold} text}",
+            "@div(Class=\"a\" Id=\"x\"){@b{bold} text}",
+            "@div(Class=\"a\"){@i{bold} text}",
+            "@div(Class=\"a\"){@b{bold} texts}",
+            "@div(Class=\"a\"){@b{bold}}",
+        ] {
+            assert!(
+                !ast_eq_ignoring_spans(&document, &parse_node(other)),
+                "'{}'",
+                other
+            );
+        }
+    }
+
+    fn first_tag(source: &str) -> ASTNodeTag {
+        return match parse_node(source) {
+            ASTNode::Root(root) => root.find_first_tag("t").unwrap().clone(),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    fn typed_attribute_accessors_check_the_type() {
+        let tag = first_tag("@t(Name=\"card\" Size=16 Hidden=true)");
+
+        assert_eq!(tag.find_attribute_str("Name"), Some("card"));
+        assert_eq!(tag.find_attribute_f64("Size"), Some(16.0));
+        assert_eq!(tag.find_attribute_bool("Hidden"), Some(true));
+
+        assert_eq!(tag.find_attribute_str("Size"), None);
+        assert_eq!(tag.find_attribute_f64("Hidden"), None);
+        assert_eq!(tag.find_attribute_bool("Name"), None);
+
+        assert_eq!(tag.find_attribute_str("Missing"), None);
+        assert_eq!(tag.find_attribute_f64("Missing"), None);
+        assert_eq!(tag.find_attribute_bool("Missing"), None);
+    }
+
+    #[test]
+    fn find_attribute_or_falls_back_to_the_default() {
+        let tag = first_tag("@t(Name=\"card\" Size=16)");
+
+        assert_eq!(tag.find_attribute_or("Size", 1.0), 16.0);
+        assert_eq!(tag.find_attribute_or("Name", 1.0), 1.0);
+        assert!(tag.find_attribute_or("Missing", true));
+        assert_eq!(
+            tag.find_attribute_or("Name", String::new()),
+            "card".to_string()
+        );
+    }
+
+    #[test]
+    fn find_attribute_ci_ignores_case() {
+        let tag = first_tag("@t(Src=\"a.png\")");
+
+        for key in ["src", "SRC", "Src", "sRc"] {
+            assert_eq!(
+                tag.find_attribute_ci(key).map(|v| v.to_string()),
+                Some("a.png".to_string()),
+                "'{}'",
+                key
+            );
+        }
+
+        assert_eq!(tag.find_attribute("src"), None);
+        assert_eq!(tag.find_attribute_ci("Missing"), None);
+    }
+
+    #[test]
+    fn find_attribute_ci_prefers_an_exact_match() {
+        let tag = first_tag("@t(SRC=\"first\" Src=\"exact\" src=\"last\")");
+
+        assert_eq!(
+            tag.find_attribute_ci("Src").map(|v| v.to_string()),
+            Some("exact".to_string())
+        );
+        assert_eq!(
+            tag.find_attribute_ci("sRC").map(|v| v.to_string()),
+            Some("first".to_string())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_keeps_the_attribute_order() {
+        let ast = parse_node("@t(z=1 a=\"s\" m=[true, 2.5] Width=50){Hello @b(k=false){world}}");
+        let json = serde_json::to_string(&ast).unwrap();
+        let round_tripped: ASTNode = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(round_tripped, ast);
+
+        let tag = match &round_tripped {
+            ASTNode::Root(root) => root.find_first_tag("t").unwrap(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            tag.attributes.keys().collect::<Vec<_>>(),
+            ["z", "a", "m", "Width"]
+        );
+        assert!(json.find("\"z\"").unwrap() < json.find("\"a\"").unwrap());
     }
 }
@@ -0,0 +1,132 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   ast_events.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodePtr;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+
+/// A single step of a depth first walk over an SRMark AST.
+pub enum AstEvent<'a> {
+    EnterRoot(&'a ASTNodeRoot),
+    EnterTag(&'a ASTNodeTag),
+    Text(&'a ASTNodeText),
+    Literal(&'a ASTNodeLiteral),
+    ExitTag(&'a ASTNodeTag),
+    ExitRoot(&'a ASTNodeRoot),
+}
+
+enum PendingEvent<'a> {
+    Enter(&'a ASTNode),
+    Exit(&'a ASTNode),
+}
+
+/// Pull based alternative to `visit_ast`, yields the same sequence of callbacks as events.
+pub struct AstEvents<'a> {
+    work_stack: Vec<PendingEvent<'a>>,
+}
+
+pub fn events(node: &ASTNode) -> AstEvents<'_> {
+    AstEvents {
+        work_stack: vec![PendingEvent::Enter(node)],
+    }
+}
+
+impl<'a> AstEvents<'a> {
+    fn push_children(&mut self, children: &'a [ASTNodePtr]) {
+        // NOTE(SR): Reversed so that the first child is at the top of the stack.
+        for child in children.iter().rev() {
+            self.work_stack.push(PendingEvent::Enter(child));
+        }
+    }
+}
+
+impl<'a> Iterator for AstEvents<'a> {
+    type Item = AstEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pending = self.work_stack.pop()?;
+
+        match pending {
+            PendingEvent::Enter(node) => match node {
+                ASTNode::Root(r) => {
+                    self.work_stack.push(PendingEvent::Exit(node));
+                    self.push_children(&r.children);
+                    return Some(AstEvent::EnterRoot(r));
+                }
+                ASTNode::Tag(t) => {
+                    self.work_stack.push(PendingEvent::Exit(node));
+                    self.push_children(&t.children);
+                    return Some(AstEvent::EnterTag(t));
+                }
+                ASTNode::Text(t) => return Some(AstEvent::Text(t)),
+                ASTNode::Literal(l) => return Some(AstEvent::Literal(l)),
+            },
+            PendingEvent::Exit(node) => match node {
+                ASTNode::Root(r) => return Some(AstEvent::ExitRoot(r)),
+                ASTNode::Tag(t) => return Some(AstEvent::ExitTag(t)),
+                _ => unreachable!("Only container nodes are exited."),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn event_names(node: &ASTNode) -> Vec<String> {
+        return events(node)
+            .map(|event| match event {
+                AstEvent::EnterRoot(_) => "enter root".to_string(),
+                AstEvent::EnterTag(tag) => format!("enter {}", tag.text),
+                AstEvent::Text(text) => format!("text {}", text.text),
+                AstEvent::Literal(literal) => format!("literal {}", literal),
+                AstEvent::ExitTag(tag) => format!("exit {}", tag.text),
+                AstEvent::ExitRoot(_) => "exit root".to_string(),
+            })
+            .collect();
+    }
+
+    #[test]
+    fn nested_document_events_are_depth_first() {
+        let root = parse("@div{ @p{Hello @b{bold}} 42 } @hr").ok().unwrap();
+
+        assert_eq!(
+            event_names(&root),
+            [
+                "enter root",
+                "enter div",
+                "enter p",
+                "text Hello",
+                "enter b",
+                "text bold",
+                "exit b",
+                "exit p",
+                "literal 42",
+                "exit div",
+                "enter hr",
+                "exit hr",
+                "exit root",
+            ]
+        );
+    }
+
+    #[test]
+    fn events_can_start_below_the_root() {
+        let root = parse("@p{A @i{B}}").ok().unwrap();
+        let ASTNode::Root(root_node) = root.as_ref() else {
+            panic!("Expected a root node");
+        };
+
+        assert_eq!(
+            event_names(&root_node.children[0]),
+            ["enter p", "text A", "enter i", "text B", "exit i", "exit p"]
+        );
+    }
+}
@@ -9,59 +9,592 @@ use crate::ast::ASTNodeRoot;
 use crate::ast::ASTNodeTag;
 use crate::ast::ASTNodeText;
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What `visit_ast` does after a callback returns.
 #[derive(PartialEq)]
 pub enum ASTProcessorVisitResult {
+    /// Visit the node's children then call its `visit_end_*`.
     Continue,
+    /// Do not visit the node's children, its `visit_end_*` is still called so begin / end calls always pair up.
     SkipChildren,
+    /// Stop the whole traversal right away, no more callbacks (including `visit_end_*`) are made
+    /// and `visit_ast` returns `Halt`. Can also be returned from a `visit_end_*`.
     Halt,
 }
 
+/// Document wide information given to a processor before it visits a tree,
+/// lets the same processor be reused across documents.
+#[derive(Default, Debug, Clone)]
+pub struct ProcessorContext {
+    pub base_path: PathBuf,
+    pub variables: HashMap<String, String>,
+    pub options: HashMap<String, String>,
+}
+
 /// Helper Interface for traversing an SRMark AST.
 pub trait IASTProcessor {
-    fn visit_begin_root(&mut self, root_node: &ASTNodeRoot) -> ASTProcessorVisitResult;
-    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag) -> ASTProcessorVisitResult;
-    fn visit_text(&mut self, text_node: &ASTNodeText) -> ASTProcessorVisitResult;
-    fn visit_literal(&mut self, literal_node: &ASTNodeLiteral) -> ASTProcessorVisitResult;
-    fn visit_end_tag(&mut self, tag_node: &ASTNodeTag) -> ();
-    fn visit_end_root(&mut self, root_node: &ASTNodeRoot) -> ();
+    /// Called by `visit_ast_with_context` before any node is visited.
+    fn set_context(&mut self, _context: &ProcessorContext) {}
+
+    // NOTE(SR): Every callback defaults to doing nothing so a processor only overrides what it cares about.
+    //           `depth` is how far the node is from where the traversal started (the root is 0, its children 1, ...).
+
+    fn visit_begin_root(
+        &mut self,
+        _root_node: &ASTNodeRoot,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_begin_tag(
+        &mut self,
+        _tag_node: &ASTNodeTag,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, _text_node: &ASTNodeText, _depth: usize) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(
+        &mut self,
+        _literal_node: &ASTNodeLiteral,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_tag(&mut self, _tag_node: &ASTNodeTag, _depth: usize) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_root(
+        &mut self,
+        _root_node: &ASTNodeRoot,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
 }
 
 pub fn visit_ast(node: &ASTNode, processor: &mut dyn IASTProcessor) -> ASTProcessorVisitResult {
-    let mut continue_processing = ASTProcessorVisitResult::Continue;
+    return visit_node(node, processor, 0);
+}
+
+fn visit_node(
+    node: &ASTNode,
+    processor: &mut dyn IASTProcessor,
+    depth: usize,
+) -> ASTProcessorVisitResult {
     match node {
         ASTNode::Root(r) => {
-            if processor.visit_begin_root(r) == ASTProcessorVisitResult::Continue {
-                for child in &r.children {
-                    continue_processing = visit_ast(&child, processor);
+            let begin_result = processor.visit_begin_root(r, depth);
+
+            if begin_result == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
 
-                    if continue_processing == ASTProcessorVisitResult::Halt {
-                        break;
+            if begin_result == ASTProcessorVisitResult::Continue {
+                for child in &r.children {
+                    if visit_node(child, processor, depth + 1) == ASTProcessorVisitResult::Halt {
+                        return ASTProcessorVisitResult::Halt;
                     }
                 }
+            }
 
-                processor.visit_end_root(r);
+            if processor.visit_end_root(r, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
             }
         }
         ASTNode::Tag(t) => {
-            if processor.visit_begin_tag(t) == ASTProcessorVisitResult::Continue {
+            let begin_result = processor.visit_begin_tag(t, depth);
+
+            if begin_result == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            if begin_result == ASTProcessorVisitResult::Continue {
                 for child in &t.children {
-                    continue_processing = visit_ast(&child, processor);
+                    if visit_node(child, processor, depth + 1) == ASTProcessorVisitResult::Halt {
+                        return ASTProcessorVisitResult::Halt;
+                    }
+                }
+            }
+
+            if processor.visit_end_tag(t, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+        }
+        ASTNode::Text(t) => {
+            if processor.visit_text(t, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+        }
+        ASTNode::Literal(l) => {
+            if processor.visit_literal(l, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+        }
+    }
+
+    return ASTProcessorVisitResult::Continue;
+}
 
-                    if continue_processing == ASTProcessorVisitResult::Halt {
-                        break;
+/// Runs `on_tag` for every tag (before its children) and `on_text` for every text node, for one off
+/// passes that do not need a whole `IASTProcessor`.
+///
+/// ```
+/// let root = srmarkup::parse("@List{ @Item{a} @Item{b} }").ok().unwrap();
+/// let mut tag_names = Vec::new();
+///
+/// srmarkup::visit_ast_with(&root, |tag| tag_names.push(tag.text.clone()), |_| {});
+///
+/// assert_eq!(tag_names, ["List", "Item", "Item"]);
+/// ```
+pub fn visit_ast_with(
+    node: &ASTNode,
+    on_tag: impl FnMut(&ASTNodeTag),
+    on_text: impl FnMut(&ASTNodeText),
+) {
+    let mut processor = ClosureProcessor { on_tag, on_text };
+
+    visit_ast(node, &mut processor);
+}
+
+struct ClosureProcessor<TagFn, TextFn> {
+    on_tag: TagFn,
+    on_text: TextFn,
+}
+
+impl<TagFn: FnMut(&ASTNodeTag), TextFn: FnMut(&ASTNodeText)> IASTProcessor
+    for ClosureProcessor<TagFn, TextFn>
+{
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, _: usize) -> ASTProcessorVisitResult {
+        (self.on_tag)(tag_node);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, _: usize) -> ASTProcessorVisitResult {
+        (self.on_text)(text_node);
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+/// Same as `IASTProcessor` but with mutable access to the nodes so a pass can rewrite the tree in place.
+/// Changes made to a node's children in its `visit_begin_*` are seen by the rest of the traversal.
+pub trait IASTMutProcessor {
+    fn visit_begin_root(
+        &mut self,
+        _root_node: &mut ASTNodeRoot,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_begin_tag(
+        &mut self,
+        _tag_node: &mut ASTNodeTag,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(
+        &mut self,
+        _text_node: &mut ASTNodeText,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(
+        &mut self,
+        _literal_node: &mut ASTNodeLiteral,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_tag(
+        &mut self,
+        _tag_node: &mut ASTNodeTag,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_root(
+        &mut self,
+        _root_node: &mut ASTNodeRoot,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+pub fn visit_ast_mut(
+    node: &mut ASTNode,
+    processor: &mut dyn IASTMutProcessor,
+) -> ASTProcessorVisitResult {
+    return visit_node_mut(node, processor, 0);
+}
+
+fn visit_node_mut(
+    node: &mut ASTNode,
+    processor: &mut dyn IASTMutProcessor,
+    depth: usize,
+) -> ASTProcessorVisitResult {
+    match node {
+        ASTNode::Root(r) => {
+            let begin_result = processor.visit_begin_root(r, depth);
+
+            if begin_result == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            if begin_result == ASTProcessorVisitResult::Continue {
+                for child in r.children.iter_mut() {
+                    if visit_node_mut(child, processor, depth + 1) == ASTProcessorVisitResult::Halt
+                    {
+                        return ASTProcessorVisitResult::Halt;
+                    }
+                }
+            }
+
+            if processor.visit_end_root(r, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+        }
+        ASTNode::Tag(t) => {
+            let begin_result = processor.visit_begin_tag(t, depth);
+
+            if begin_result == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            if begin_result == ASTProcessorVisitResult::Continue {
+                for child in t.children.iter_mut() {
+                    if visit_node_mut(child, processor, depth + 1) == ASTProcessorVisitResult::Halt
+                    {
+                        return ASTProcessorVisitResult::Halt;
                     }
                 }
+            }
 
-                processor.visit_end_tag(t);
+            if processor.visit_end_tag(t, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
             }
         }
         ASTNode::Text(t) => {
-            continue_processing = processor.visit_text(t);
+            if processor.visit_text(t, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
         }
         ASTNode::Literal(l) => {
-            continue_processing = processor.visit_literal(l);
+            if processor.visit_literal(l, depth) == ASTProcessorVisitResult::Halt {
+                return ASTProcessorVisitResult::Halt;
+            }
+        }
+    }
+
+    return ASTProcessorVisitResult::Continue;
+}
+
+pub fn visit_ast_with_context(
+    node: &ASTNode,
+    processor: &mut dyn IASTProcessor,
+    context: &ProcessorContext,
+) -> ASTProcessorVisitResult {
+    processor.set_context(context);
+
+    return visit_ast(node, processor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    struct ContextProcessor {
+        base_path: PathBuf,
+        variables: HashMap<String, String>,
+        tag_count: usize,
+    }
+
+    impl IASTProcessor for ContextProcessor {
+        fn set_context(&mut self, context: &ProcessorContext) {
+            self.base_path = context.base_path.clone();
+            self.variables = context.variables.clone();
+        }
+
+        fn visit_begin_tag(&mut self, _: &ASTNodeTag, _: usize) -> ASTProcessorVisitResult {
+            self.tag_count += 1;
+            return ASTProcessorVisitResult::Continue;
+        }
+    }
+
+    #[test]
+    fn context_reaches_the_processor_before_the_visit() {
+        let root = parse("@p{A} @p{B}").ok().unwrap();
+        let mut processor = ContextProcessor {
+            base_path: PathBuf::new(),
+            variables: HashMap::new(),
+            tag_count: 0,
+        };
+
+        for base_path in ["posts/2024", "drafts"] {
+            let context = ProcessorContext {
+                base_path: PathBuf::from(base_path),
+                variables: HashMap::from([("site".to_string(), "blog".to_string())]),
+                ..Default::default()
+            };
+
+            visit_ast_with_context(&root, &mut processor, &context);
+
+            assert_eq!(processor.base_path, PathBuf::from(base_path));
+            assert_eq!(processor.variables["site"], "blog");
+        }
+
+        assert_eq!(processor.tag_count, 4);
+    }
+
+    /// Only overrides `visit_begin_tag`, everything else is the trait's default.
+    struct TagNameProcessor {
+        tag_names: Vec<String>,
+    }
+
+    impl IASTProcessor for TagNameProcessor {
+        fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, _: usize) -> ASTProcessorVisitResult {
+            self.tag_names.push(tag_node.text.clone());
+            return ASTProcessorVisitResult::Continue;
+        }
+    }
+
+    #[test]
+    fn default_callbacks_continue_the_traversal() {
+        let root = parse("Intro @list(Kind=\"a\"){ @item{one 1} @item{two @b{true}} } Outro")
+            .ok()
+            .unwrap();
+        let mut processor = TagNameProcessor {
+            tag_names: Vec::new(),
+        };
+
+        assert!(visit_ast(&root, &mut processor) == ASTProcessorVisitResult::Continue);
+        assert_eq!(processor.tag_names, ["list", "item", "item", "b"]);
+    }
+
+    /// Rewrites `link` sources and appends a `@toc` to the root's children before they are visited.
+    struct RewriteProcessor {
+        visited_tags: Vec<String>,
+    }
+
+    impl IASTMutProcessor for RewriteProcessor {
+        fn visit_begin_root(
+            &mut self,
+            root_node: &mut ASTNodeRoot,
+            _: usize,
+        ) -> ASTProcessorVisitResult {
+            root_node
+                .children
+                .push(Box::new(ASTNode::Tag(ASTNodeTag::new("toc".to_string()))));
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_begin_tag(
+            &mut self,
+            tag_node: &mut ASTNodeTag,
+            _: usize,
+        ) -> ASTProcessorVisitResult {
+            self.visited_tags.push(tag_node.text.clone());
+
+            if let Some(ASTNodeLiteral::Str(src)) = tag_node.attributes.get_mut("Src") {
+                *src = format!("https://example.com/{}", src);
+            }
+
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_text(&mut self, text_node: &mut ASTNodeText, _: usize) -> ASTProcessorVisitResult {
+            text_node.text = text_node.text.to_uppercase();
+            return ASTProcessorVisitResult::Continue;
         }
     }
 
-    return continue_processing;
+    #[test]
+    fn mutable_processors_rewrite_the_tree_in_place() {
+        let mut root = parse("@link(Src=\"a.html\"){here} and more").ok().unwrap();
+        let mut processor = RewriteProcessor {
+            visited_tags: Vec::new(),
+        };
+
+        visit_ast_mut(&mut root, &mut processor);
+
+        assert_eq!(
+            root,
+            parse("@link(Src=\"https://example.com/a.html\"){HERE} AND MORE @toc")
+                .ok()
+                .unwrap()
+        );
+        assert_eq!(processor.visited_tags, ["link", "toc"]);
+    }
+
+    /// Writes down every callback, returning `SkipChildren` / `Halt` for the tags named by the test.
+    #[derive(Default)]
+    struct EventLog {
+        events: Vec<String>,
+        skip_children_of: &'static str,
+        halt_on_begin: &'static str,
+        halt_on_end: &'static str,
+    }
+
+    impl IASTProcessor for EventLog {
+        fn visit_begin_root(&mut self, _: &ASTNodeRoot, depth: usize) -> ASTProcessorVisitResult {
+            self.events.push(format!("begin root {}", depth));
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_begin_tag(
+            &mut self,
+            tag_node: &ASTNodeTag,
+            depth: usize,
+        ) -> ASTProcessorVisitResult {
+            self.events
+                .push(format!("begin {} {}", tag_node.text, depth));
+
+            if tag_node.text == self.halt_on_begin {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            if tag_node.text == self.skip_children_of {
+                return ASTProcessorVisitResult::SkipChildren;
+            }
+
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_text(&mut self, text_node: &ASTNodeText, depth: usize) -> ASTProcessorVisitResult {
+            self.events
+                .push(format!("text {} {}", text_node.text, depth));
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_end_tag(
+            &mut self,
+            tag_node: &ASTNodeTag,
+            depth: usize,
+        ) -> ASTProcessorVisitResult {
+            self.events.push(format!("end {} {}", tag_node.text, depth));
+
+            if tag_node.text == self.halt_on_end {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_end_root(&mut self, _: &ASTNodeRoot, depth: usize) -> ASTProcessorVisitResult {
+            self.events.push(format!("end root {}", depth));
+            return ASTProcessorVisitResult::Continue;
+        }
+    }
+
+    #[test]
+    fn skip_children_still_ends_the_tag() {
+        let root = parse("@a{@skip{@inner{x}} @b{y}}").ok().unwrap();
+        let mut log = EventLog {
+            skip_children_of: "skip",
+            ..Default::default()
+        };
+
+        assert!(visit_ast(&root, &mut log) == ASTProcessorVisitResult::Continue);
+        assert_eq!(
+            log.events,
+            [
+                "begin root 0",
+                "begin a 1",
+                "begin skip 2",
+                "end skip 2",
+                "begin b 2",
+                "text y 3",
+                "end b 2",
+                "end a 1",
+                "end root 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn halt_from_begin_stops_before_any_end() {
+        let root = parse("@a{@halt{x} @b{y}}").ok().unwrap();
+        let mut log = EventLog {
+            halt_on_begin: "halt",
+            ..Default::default()
+        };
+
+        assert!(visit_ast(&root, &mut log) == ASTProcessorVisitResult::Halt);
+        assert_eq!(log.events, ["begin root 0", "begin a 1", "begin halt 2"]);
+    }
+
+    #[test]
+    fn halt_from_end_stops_the_traversal() {
+        let root = parse("@a{@halt{x} @b{y}} @c").ok().unwrap();
+        let mut log = EventLog {
+            halt_on_end: "halt",
+            ..Default::default()
+        };
+
+        assert!(visit_ast(&root, &mut log) == ASTProcessorVisitResult::Halt);
+        assert_eq!(
+            log.events,
+            [
+                "begin root 0",
+                "begin a 1",
+                "begin halt 2",
+                "text x 3",
+                "end halt 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn depth_counts_from_where_the_traversal_starts() {
+        let root = parse("top @h1{@b{@i{deep}}} @p{x}").ok().unwrap();
+        let mut log = EventLog::default();
+
+        visit_ast(&root, &mut log);
+
+        assert_eq!(
+            log.events,
+            [
+                "begin root 0",
+                "text top 1",
+                "begin h1 1",
+                "begin b 2",
+                "begin i 3",
+                "text deep 4",
+                "end i 3",
+                "end b 2",
+                "end h1 1",
+                "begin p 1",
+                "text x 2",
+                "end p 1",
+                "end root 0",
+            ]
+        );
+
+        let h1 = match root.as_ref() {
+            ASTNode::Root(root_node) => root_node.children[1].as_ref(),
+            _ => unreachable!(),
+        };
+        let mut log = EventLog::default();
+
+        visit_ast(h1, &mut log);
+
+        assert_eq!(log.events[..3], ["begin h1 0", "begin b 1", "begin i 2"]);
+    }
 }
@@ -0,0 +1,74 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   ast_stats.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast_processor::visit_ast;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+
+/// Node counts of a document, handy for checking an edit to a document did not drop any content.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AstStats {
+    pub tag_count: usize,
+    pub text_count: usize,
+    pub literal_count: usize,
+    pub attribute_count: usize, // Summed over every tag.
+    pub max_depth: usize, // Of the deepest node, the root is 0 (same as the `depth` given to an `IASTProcessor`).
+}
+
+struct AstStatsProcessor {
+    stats: AstStats,
+}
+
+impl AstStatsProcessor {
+    fn reached_depth(self: &mut Self, depth: usize) {
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+    }
+}
+
+impl IASTProcessor for AstStatsProcessor {
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, depth: usize) -> ASTProcessorVisitResult {
+        self.stats.tag_count += 1;
+        self.stats.attribute_count += tag_node.attributes.len();
+        self.reached_depth(depth);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, _: &ASTNodeText, depth: usize) -> ASTProcessorVisitResult {
+        self.stats.text_count += 1;
+        self.reached_depth(depth);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(&mut self, _: &ASTNodeLiteral, depth: usize) -> ASTProcessorVisitResult {
+        self.stats.literal_count += 1;
+        self.reached_depth(depth);
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+/// Counts every kind of node in the tree in a single traversal.
+///
+/// ```
+/// let root = srmarkup::parse("@p(class=\"intro\"){ Hello @b{World} }").ok().unwrap();
+/// let stats = srmarkup::ast_stats(&root);
+///
+/// assert_eq!(stats.tag_count, 2);
+/// assert_eq!(stats.text_count, 2);
+/// assert_eq!(stats.attribute_count, 1);
+/// assert_eq!(stats.max_depth, 3);
+/// ```
+pub fn ast_stats(root: &ASTNode) -> AstStats {
+    let mut processor = AstStatsProcessor {
+        stats: AstStats::default(),
+    };
+
+    visit_ast(root, &mut processor);
+
+    return processor.stats;
+}
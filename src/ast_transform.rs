@@ -0,0 +1,305 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   ast_transform.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeList;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::AttributeMap;
+
+use std::collections::HashMap;
+
+/// Replaces a tag with its only child when the tag is one of `transparent_tags`,
+/// has no attributes and that child is itself a tag (`@div{@div{content}}` becomes `@div{content}`).
+pub fn collapse_redundant_wrappers(node: &mut ASTNode, transparent_tags: &[&str]) {
+    match node {
+        ASTNode::Root(r) => collapse_children(&mut r.children, transparent_tags),
+        ASTNode::Tag(t) => collapse_children(&mut t.children, transparent_tags),
+        ASTNode::Text(_) | ASTNode::Literal(_) => {}
+    }
+}
+
+fn collapse_children(children: &mut ASTNodeList, transparent_tags: &[&str]) {
+    for child in children.iter_mut() {
+        collapse_redundant_wrappers(child, transparent_tags);
+
+        // NOTE(SR): A loop since `@div{@div{@div{x}}}` is a chain of wrappers.
+        while is_redundant_wrapper(child, transparent_tags) {
+            let inner_node = match child.as_mut() {
+                ASTNode::Tag(t) => t.children.pop().unwrap(),
+                _ => unreachable!("Only tags are wrappers."),
+            };
+
+            *child = inner_node;
+        }
+    }
+}
+
+fn is_redundant_wrapper(node: &ASTNode, transparent_tags: &[&str]) -> bool {
+    return match node {
+        ASTNode::Tag(t) => {
+            transparent_tags.contains(&t.text.as_str())
+                && t.attributes.is_empty()
+                && t.children.len() == 1
+                && matches!(t.children[0].as_ref(), ASTNode::Tag(_))
+        }
+        _ => false,
+    };
+}
+
+/// An invocation of a macro inside of its own expansion, or a chain of them deeper than the limit.
+#[derive(Debug)]
+pub struct MacroExpansionError {
+    pub name: String,
+    pub line_number: usize,
+    pub max_depth: u32,
+}
+
+impl std::fmt::Display for MacroExpansionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Line({}): expanding '@{}' went deeper than {} macros, is it recursive?",
+            self.line_number, self.name, self.max_depth
+        )
+    }
+}
+
+/// Removes every `@define(Name="card"){ ... }` from the tree and replaces each `@card(...)` with a copy
+/// of the definition's body, where `${param}` in text and string values is replaced by the invocation's
+/// `param` attribute (unknown parameters are left as written). The invocation's own body is dropped.
+///
+/// Expansions may use other macros, up to `max_depth` levels deep.
+pub fn expand_macros(node: &mut ASTNode, max_depth: u32) -> Result<(), MacroExpansionError> {
+    let mut definitions = HashMap::new();
+
+    collect_macro_definitions(node, &mut definitions);
+
+    return match node {
+        ASTNode::Root(r) => expand_children(&mut r.children, &definitions, max_depth, 0),
+        ASTNode::Tag(t) => expand_children(&mut t.children, &definitions, max_depth, 0),
+        ASTNode::Text(_) | ASTNode::Literal(_) => Ok(()),
+    };
+}
+
+fn collect_macro_definitions(node: &mut ASTNode, definitions: &mut HashMap<String, ASTNodeList>) {
+    let children = match node {
+        ASTNode::Root(r) => &mut r.children,
+        ASTNode::Tag(t) => &mut t.children,
+        ASTNode::Text(_) | ASTNode::Literal(_) => return,
+    };
+    let mut index = 0;
+
+    while index < children.len() {
+        let macro_name = match children[index].as_ref() {
+            ASTNode::Tag(t) if t.text == "define" => t.find_attribute_str("Name").map(String::from),
+            _ => None,
+        };
+
+        if let Some(macro_name) = macro_name {
+            if let ASTNode::Tag(definition) = *children.remove(index) {
+                definitions.insert(macro_name, definition.children);
+            }
+        } else {
+            collect_macro_definitions(&mut children[index], definitions);
+            index += 1;
+        }
+    }
+}
+
+fn expand_children(
+    children: &mut ASTNodeList,
+    definitions: &HashMap<String, ASTNodeList>,
+    max_depth: u32,
+    depth: u32,
+) -> Result<(), MacroExpansionError> {
+    let mut index = 0;
+
+    while index < children.len() {
+        let expansion = match children[index].as_ref() {
+            ASTNode::Tag(invocation) => match definitions.get(&invocation.text) {
+                Some(_) if depth >= max_depth => {
+                    return Err(MacroExpansionError {
+                        name: invocation.text.clone(),
+                        line_number: invocation.span.start_line,
+                        max_depth,
+                    });
+                }
+                Some(body) => {
+                    let mut expansion = body.clone();
+
+                    for expanded_node in expansion.iter_mut() {
+                        substitute_parameters(expanded_node, &invocation.attributes);
+                    }
+
+                    Some(expansion)
+                }
+                None => None,
+            },
+            _ => None,
+        };
+
+        match expansion {
+            Some(mut expansion) => {
+                expand_children(&mut expansion, definitions, max_depth, depth + 1)?;
+
+                let expansion_length = expansion.len();
+                children.splice(index..=index, expansion);
+                index += expansion_length;
+            }
+            None => {
+                if let ASTNode::Tag(t) = children[index].as_mut() {
+                    expand_children(&mut t.children, definitions, max_depth, depth)?;
+                }
+
+                index += 1;
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+fn substitute_parameters(node: &mut ASTNode, arguments: &AttributeMap) {
+    match node {
+        ASTNode::Root(r) => {
+            for child in r.children.iter_mut() {
+                substitute_parameters(child, arguments);
+            }
+        }
+        ASTNode::Tag(t) => {
+            for value in t.attributes.values_mut() {
+                substitute_literal_parameters(value, arguments);
+            }
+
+            for child in t.children.iter_mut() {
+                substitute_parameters(child, arguments);
+            }
+        }
+        ASTNode::Text(t) => t.text = substitute_text_parameters(&t.text, arguments),
+        ASTNode::Literal(l) => substitute_literal_parameters(l, arguments),
+    }
+}
+
+fn substitute_literal_parameters(literal: &mut ASTNodeLiteral, arguments: &AttributeMap) {
+    match literal {
+        ASTNodeLiteral::Str(value) => *value = substitute_text_parameters(value, arguments),
+        ASTNodeLiteral::List(items) => {
+            for item in items.iter_mut() {
+                substitute_literal_parameters(item, arguments);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn substitute_text_parameters(text: &str, arguments: &AttributeMap) -> String {
+    let mut result = String::new();
+    let mut remaining_text = text;
+
+    while let Some(placeholder_start) = remaining_text.find("${") {
+        let name_start = placeholder_start + 2;
+        let name_end = match remaining_text[name_start..].find('}') {
+            Some(name_length) => name_start + name_length,
+            None => break,
+        };
+
+        result.push_str(&remaining_text[..placeholder_start]);
+
+        match arguments.get(&remaining_text[name_start..name_end]) {
+            Some(value) => result.push_str(&value.to_string()),
+            None => result.push_str(&remaining_text[placeholder_start..=name_end]),
+        }
+
+        remaining_text = &remaining_text[name_end + 1..];
+    }
+
+    result.push_str(remaining_text);
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::parser::Parser;
+
+    fn collapsed(source: &str, transparent_tags: &[&str]) -> ASTNode {
+        let mut root = parse(source).ok().unwrap();
+        collapse_redundant_wrappers(&mut root, transparent_tags);
+
+        return *root;
+    }
+
+    fn parsed(source: &str) -> ASTNode {
+        return *parse(source).ok().unwrap();
+    }
+
+    fn expanded(source: &str, max_depth: u32) -> Result<ASTNode, MacroExpansionError> {
+        let mut parser = Parser::new(source.to_string());
+        parser.macro_placeholders = true;
+
+        let mut root = parser.parse().ok().unwrap();
+        expand_macros(&mut root, max_depth)?;
+
+        return Ok(*root);
+    }
+
+    #[test]
+    fn nested_wrappers_collapse_into_one_level() {
+        assert_eq!(
+            collapsed("@div{@div{content}}", &["div"]),
+            parsed("@div{content}")
+        );
+        assert_eq!(
+            collapsed("@section{@div{@div{@div{@p{x}}}}}", &["div"]),
+            parsed("@section{@p{x}}")
+        );
+    }
+
+    #[test]
+    fn wrappers_with_attributes_or_siblings_are_kept() {
+        for source in [
+            "@div(Class=\"card\"){@div{content}}",
+            "@div{@div{a} @div{b}}",
+            "@div{@div{a} text}",
+            "@section{@section{content}}",
+        ] {
+            assert_eq!(collapsed(source, &["div"]), parsed(source), "'{}'", source);
+        }
+    }
+
+    #[test]
+    fn macros_are_replaced_by_their_body() {
+        assert_eq!(
+            expanded("@define(Name=\"sig\"){@i{Thanks}} @p{A} @sig @p{B} @sig", 8).unwrap(),
+            parsed("@p{A} @i{Thanks} @p{B} @i{Thanks}")
+        );
+    }
+
+    #[test]
+    fn parameters_are_substituted_in_text_and_strings() {
+        let source =
+            "@define(Name=\"card\"){@div(Class=\"card ${kind}\"){Hello ${who}, ${unknown}}}\n\
+                      @card(who=\"World\" kind=\"wide\")";
+
+        assert_eq!(
+            expanded(source, 8).unwrap(),
+            parsed("@div(Class=\"card wide\"){Hello World, $\\{unknown\\}}")
+        );
+    }
+
+    #[test]
+    fn recursive_macros_stop_at_the_depth_limit() {
+        let error = expanded("@define(Name=\"loop\"){@b{@loop}}\n\n@loop", 4).unwrap_err();
+
+        assert_eq!(error.name, "loop");
+        assert_eq!(error.max_depth, 4);
+        assert_eq!(
+            error.to_string(),
+            "Line(1): expanding '@loop' went deeper than 4 macros, is it recursive?"
+        );
+    }
+}
@@ -11,9 +11,10 @@
 //   To uninstall rustup : `rustup self uninstall`
 //       Open Local Docs : `rustup doc`
 
-use srmarkup;
-use srmarkup::ASTNodeLiteral;
 use srmarkup::ASTProcessorVisitResult;
+use srmarkup::DebugProcessor;
+use srmarkup::IASTProcessor;
+use srmarkup::JsonProcessor;
 
 use std::fs::File;
 use std::io::Read;
@@ -25,17 +26,41 @@ struct Options {
     #[structopt(short, long)]
     pub verbose: bool,
 
+    /// Number of indent characters per nesting level.
     #[structopt(short, long, default_value = "2")]
     pub indent_spaces: u32,
 
+    /// Character used for indentation, either `space` or `tab`.
+    #[structopt(long, default_value = "space", possible_values = &["space", "tab"])]
+    pub indent_char: String,
+
+    /// `text` for the indented debug dump, `json` for the tree as a json document.
+    #[structopt(long, default_value = "text", possible_values = &["text", "json"])]
+    pub format: String,
+
+    /// With `--format json` also write each node's source line / column / byte offset.
+    #[structopt(long)]
+    pub spans: bool,
+
+    /// Keep number literals as they were written (`16.0` rather than `16`).
+    #[structopt(long)]
+    pub preserve_number_text: bool,
+
+    /// File to dump, `-` (or leaving it out) reads the document from standard input.
     #[structopt(long)]
-    pub input: String,
+    pub input: Option<String>,
 }
 
 fn main() {
     let options = Options::from_args();
 
-    println!("SR-Markup Dump");
+    let is_json = options.format == "json";
+
+    // NOTE(SR): The json output is meant for other programs so it is left without the banner.
+    if !is_json {
+        println!("SR-Markup Dump");
+    }
+
     if options.verbose {
         let args = std::env::args();
         let cwd = std::env::current_dir();
@@ -46,7 +71,12 @@ fn main() {
         }
     }
 
-    let input_file = File::open(&options.input);
+    let input_name = options.input.as_deref().unwrap_or("-");
+    let input_file: std::io::Result<Box<dyn Read>> = if input_name == "-" {
+        Ok(Box::new(std::io::stdin()))
+    } else {
+        File::open(input_name).map(|file| Box::new(file) as Box<dyn Read>)
+    };
 
     match input_file {
         Ok(mut file) => {
@@ -55,126 +85,51 @@ fn main() {
 
             match source_size {
                 Ok(_) => {
-                    let mut parser = srmarkup::Parser::new(source);
+                    let mut parser = srmarkup::Parser::new(source.clone());
+                    parser.preserve_number_text = options.preserve_number_text;
+
                     let parse_result: srmarkup::ParseResult = parser.parse();
 
                     match parse_result {
                         Ok(root_node) => {
-                            let visit_result = srmarkup::visit_ast(
-                                &root_node,
-                                &mut DebugProcessor::new(options.indent_spaces),
-                            );
+                            let indent_char = if options.indent_char == "tab" {
+                                '\t'
+                            } else {
+                                ' '
+                            };
+                            let mut debug_processor =
+                                DebugProcessor::new(options.indent_spaces, indent_char);
+                            let mut json_processor = JsonProcessor::new();
+                            json_processor.spans = options.spans;
+                            let processor: &mut dyn IASTProcessor = if is_json {
+                                &mut json_processor
+                            } else {
+                                &mut debug_processor
+                            };
+                            let visit_result = srmarkup::visit_ast(&root_node, processor);
+
+                            if is_json {
+                                print!("{}", json_processor.output);
+                            } else {
+                                print!("{}", debug_processor.output);
+                            }
 
                             if visit_result == ASTProcessorVisitResult::Halt {
                                 println!("Failed to visit all AST nodes.");
                             }
                         }
                         Err(error_log) => {
-                            eprintln!("Parse Error:");
-                            for err in &error_log.errors {
-                                eprintln!("  Line({}): {}\n", err.line_number, err.message);
-                            }
+                            eprint!("{}", error_log.render(&source));
                         }
                     }
                 }
                 Err(msg) => {
-                    eprintln!(
-                        "[ERROR] Failed to read file ('{}'), {}.",
-                        options.input, msg
-                    );
+                    eprintln!("[ERROR] Failed to read file ('{}'), {}.", input_name, msg);
                 }
             }
         }
         Err(msg) => {
-            eprintln!(
-                "[ERROR] Failed to load file ('{}'), {}.",
-                options.input, msg
-            );
+            eprintln!("[ERROR] Failed to load file ('{}'), {}.", input_name, msg);
         }
     }
 }
-
-struct DebugProcessor {
-    current_indent: u32,
-    num_spaces_per_indent: u32,
-}
-
-impl DebugProcessor {
-    pub fn new(num_spaces_per_indent: u32) -> Self {
-        DebugProcessor {
-            current_indent: 0,
-            num_spaces_per_indent,
-        }
-    }
-
-    fn indent(&mut self) {
-        self.current_indent += 1;
-    }
-
-    fn print_indent(&self) {
-        for _i in 0..self.current_indent {
-            for _j in 0..self.num_spaces_per_indent {
-                print!(" ");
-            }
-        }
-    }
-
-    fn unindent(&mut self) {
-        self.current_indent -= 1;
-    }
-}
-
-impl srmarkup::IASTProcessor for DebugProcessor {
-    fn visit_begin_root(&mut self, _: &srmarkup::ASTNodeRoot) -> ASTProcessorVisitResult {
-        println!("(root-begin){{");
-        self.indent();
-        return ASTProcessorVisitResult::Continue;
-    }
-
-    fn visit_begin_tag(&mut self, tag_node: &srmarkup::ASTNodeTag) -> ASTProcessorVisitResult {
-        self.print_indent();
-        println!("Tag({}) {{", tag_node.text);
-        self.indent();
-
-        if !tag_node.attributes.is_empty() {
-            self.indent();
-
-            self.print_indent();
-            println!("Attributes: ");
-
-            self.indent();
-            for attrib in &tag_node.attributes {
-                self.print_indent();
-                println!("'{}' = {:?}", attrib.0, attrib.1);
-            }
-            self.unindent();
-
-            self.unindent();
-        }
-        return ASTProcessorVisitResult::Continue;
-    }
-
-    fn visit_text(&mut self, text_node: &srmarkup::ASTNodeText) -> ASTProcessorVisitResult {
-        self.print_indent();
-        println!("TEXT({})", text_node.text);
-        return ASTProcessorVisitResult::Continue;
-    }
-
-    fn visit_literal(&mut self, literal_node: &ASTNodeLiteral) -> ASTProcessorVisitResult {
-        self.print_indent();
-        println!("LITERAL({:?})", literal_node);
-        return ASTProcessorVisitResult::Continue;
-    }
-
-    fn visit_end_tag(&mut self, _: &srmarkup::ASTNodeTag) {
-        self.unindent();
-        self.print_indent();
-        println!("}}");
-    }
-
-    fn visit_end_root(&mut self, _: &srmarkup::ASTNodeRoot) {
-        self.unindent();
-        self.print_indent();
-        println!("}}(root-end)");
-    }
-}
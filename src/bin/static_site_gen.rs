@@ -1,22 +1,32 @@
-use srmarkup::{self, ASTNodeText};
+use srmarkup::{self, ASTNodeText, IASTProcessor};
 
 use structopt::StructOpt;
 
 use minijinja::{context, Environment};
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 
 pub mod html {
-    use std::collections::HashMap;
     use std::io::Write;
 
     pub type ElementTagID = u32;
 
+    /// Decides where `Element::render` may add whitespace when pretty printing.
+    #[derive(Clone, Copy, PartialEq)]
+    pub enum ElementLayout {
+        Block,        // Own line, children indented.
+        Inline, // Written in line with the content around it, nothing is added to its content.
+        Preformatted, // Own line, nothing is added to the content as whitespace is displayed as is.
+    }
+
     pub struct ElementTag {
         pub start_tag: String,
         pub end_tag: Option<String>, // Same as `start_tag` if not specified.
         pub is_void_element: bool,
+        pub layout: ElementLayout,
     }
 
     pub type ElementID = u32;
@@ -35,54 +45,263 @@ pub mod html {
         Text(String),
     }
 
+    /// Value of an html attribute, a boolean attribute is written as just its name (`<video controls>`)
+    /// while text is always quoted, even when empty (`<img alt="">`).
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum AttrValue {
+        Boolean,
+        Text(String),
+    }
+
+    impl From<String> for AttrValue {
+        fn from(value: String) -> Self {
+            return AttrValue::Text(value);
+        }
+    }
+
+    impl From<&str> for AttrValue {
+        fn from(value: &str) -> Self {
+            return AttrValue::Text(value.to_string());
+        }
+    }
+
     pub struct Element {
         pub tag: ElementTagID,
-        pub attributes: HashMap<String, String>,
+        pub attributes: Vec<(String, AttrValue)>, // In the order they were first set so the output is stable.
         pub contents: Vec<ElementContent>,
         pub is_comment: bool, // Print will be wrapped in "<!--" and "-->".
     }
 
+    /// How `Element::render` lays out the html.
+    pub struct RenderOptions {
+        pub pretty: bool, // Block tags and each run of inline content on their own line, otherwise nothing is added to the content.
+        pub indent: String, // Written once per nesting level at the start of each line when `pretty`.
+        pub collapse_whitespace: bool, // Runs of whitespace in text become a single space (outside of `<pre>`).
+        pub self_close_void: bool, // Void elements are written XHTML style (`<br />`) rather than `<br>`.
+        in_preformatted: bool, // Inside of a `<pre>`, where whitespace is never collapsed or dropped.
+    }
+
+    impl Default for RenderOptions {
+        fn default() -> Self {
+            RenderOptions {
+                pretty: true,
+                indent: String::new(),
+                collapse_whitespace: false,
+                self_close_void: false,
+                in_preformatted: false,
+            }
+        }
+    }
+
+    impl RenderOptions {
+        pub fn compact() -> Self {
+            RenderOptions {
+                pretty: false,
+                indent: String::new(),
+                collapse_whitespace: false,
+                self_close_void: false,
+                in_preformatted: false,
+            }
+        }
+
+        /// Smallest output that still displays the same, for `--minify`.
+        pub fn minified() -> Self {
+            RenderOptions {
+                collapse_whitespace: true,
+                ..RenderOptions::compact()
+            }
+        }
+
+        /// Same as `self` without the pretty printing, for content that has to stay on one line.
+        fn inline_options(&self) -> RenderOptions {
+            return RenderOptions {
+                collapse_whitespace: self.collapse_whitespace,
+                self_close_void: self.self_close_void,
+                in_preformatted: self.in_preformatted,
+                ..RenderOptions::compact()
+            };
+        }
+
+        fn write_text(&self, writer: &mut dyn Write, text: &str) {
+            // NOTE(SR): A leading / trailing run becomes a space too, it separates the text from its neighbours.
+            if self.collapse_whitespace && !self.in_preformatted {
+                let mut collapsed = String::with_capacity(text.len());
+
+                for c in text.chars() {
+                    if !c.is_whitespace() {
+                        collapsed.push(c);
+                    } else if !collapsed.ends_with(' ') {
+                        collapsed.push(' ');
+                    }
+                }
+
+                let _ = write!(writer, "{}", collapsed);
+            } else {
+                let _ = write!(writer, "{}", text);
+            }
+        }
+
+        fn write_indent(&self, writer: &mut dyn Write, depth: usize) {
+            if self.pretty {
+                let _ = write!(writer, "{}", self.indent.repeat(depth));
+            }
+        }
+
+        fn write_newline(&self, writer: &mut dyn Write) {
+            if self.pretty {
+                let _ = writeln!(writer);
+            }
+        }
+    }
+
     impl Element {
-        pub fn render(&self, writer: &mut dyn Write, doc: &Document) {
+        pub fn render(
+            &self,
+            writer: &mut dyn Write,
+            doc: &Document,
+            options: &RenderOptions,
+            depth: usize,
+        ) {
             let tag_data = &doc.tags[self.tag as usize];
 
             if self.is_comment {
-                let _ = write!(writer, "<!--\n");
+                let _ = write!(writer, "<!--");
+                options.write_newline(writer);
             }
 
+            options.write_indent(writer, depth);
             let _ = write!(writer, "<{}", tag_data.start_tag);
 
             for attrib in self.attributes.iter() {
                 let _ = write!(writer, " {}", attrib.0);
 
-                if !attrib.1.is_empty() {
-                    let _ = write!(writer, "=\"{}\"", attrib.1);
+                // NOTE(SR): Values are stored unescaped, text content is escaped when it is pushed so is left alone here.
+                if let AttrValue::Text(value) = &attrib.1 {
+                    let _ = write!(writer, "=\"{}\"", escape(value));
                 }
             }
 
-            let _ = write!(writer, ">\n");
+            // NOTE(SR): Void elements can not have content so never get a closing tag.
+            if tag_data.is_void_element && options.self_close_void {
+                let _ = write!(writer, " />");
+            } else {
+                let _ = write!(writer, ">");
+            }
 
-            if !tag_data.is_void_element {
-                for item in self.contents.iter() {
-                    match item {
-                        ElementContent::Element(element_id) => {
-                            doc.elements[*element_id as usize].render(writer, doc);
-                        }
-                        ElementContent::Text(txt) => {
-                            let _ = write!(writer, "{}", txt);
-                        }
-                    }
-                }
+            if tag_data.is_void_element {
+                options.write_newline(writer);
+            } else {
+                let compact_options = RenderOptions {
+                    in_preformatted: options.in_preformatted
+                        || tag_data.layout == ElementLayout::Preformatted,
+                    ..options.inline_options()
+                };
+                let content_options = if tag_data.layout == ElementLayout::Block {
+                    options
+                } else {
+                    &compact_options
+                };
+
+                content_options.write_newline(writer);
+                self.render_contents(writer, doc, content_options, depth + 1);
+                content_options.write_indent(writer, depth);
 
-                if tag_data.end_tag.is_some() {
-                    let _ = write!(writer, "\n</{}>\n", tag_data.end_tag.as_ref().unwrap());
+                if let Some(end_tag) = &tag_data.end_tag {
+                    let _ = write!(writer, "</{}>", end_tag);
                 } else {
-                    let _ = write!(writer, "\n</{}>\n", tag_data.start_tag);
+                    let _ = write!(writer, "</{}>", tag_data.start_tag);
                 }
+
+                options.write_newline(writer);
             }
 
             if self.is_comment {
-                let _ = write!(writer, "-->\n");
+                let _ = write!(writer, "-->");
+                options.write_newline(writer);
+            }
+        }
+    }
+
+    impl Element {
+        /// Writes the contents at `depth`, see `render`.
+        ///
+        /// Inline content is written exactly as given, nothing is added between two neighbours so
+        /// `<b>un</b>believable` stays one word. When pretty printing each run of inline content goes
+        /// on its own line, and only block children are separated by newlines.
+        pub fn render_contents(
+            &self,
+            writer: &mut dyn Write,
+            doc: &Document,
+            options: &RenderOptions,
+            depth: usize,
+        ) {
+            let inline_options = options.inline_options();
+            let mut is_line_started = false;
+
+            for (index, item) in self.contents.iter().enumerate() {
+                let is_after_block =
+                    index != 0 && !doc.is_inline_content(&self.contents[index - 1]);
+                let is_before_block = self
+                    .contents
+                    .get(index + 1)
+                    .is_some_and(|next| !doc.is_inline_content(next));
+
+                if !doc.is_inline_content(item) {
+                    doc.render_content(writer, item, options, depth);
+                    continue;
+                }
+
+                if !options.pretty {
+                    let is_blank_text =
+                        matches!(item, ElementContent::Text(txt) if txt.trim().is_empty());
+
+                    // NOTE(SR): The whitespace between a block tag and its neighbours is not displayed.
+                    if !(is_blank_text
+                        && !options.in_preformatted
+                        && (is_after_block || is_before_block))
+                    {
+                        doc.render_content(writer, item, options, depth);
+                    }
+                    continue;
+                }
+
+                let is_line_end = index + 1 == self.contents.len() || is_before_block;
+
+                match item {
+                    ElementContent::Text(txt) => {
+                        let mut txt = txt.as_str();
+
+                        // NOTE(SR): The line break and indent already separate the line from its neighbours.
+                        if !is_line_started {
+                            txt = txt.trim_start();
+                        }
+                        if is_line_end {
+                            txt = txt.trim_end();
+                        }
+
+                        if !txt.is_empty() {
+                            if !is_line_started {
+                                options.write_indent(writer, depth);
+                                is_line_started = true;
+                            }
+
+                            options.write_text(writer, txt);
+                        }
+                    }
+                    ElementContent::Element(_) => {
+                        if !is_line_started {
+                            options.write_indent(writer, depth);
+                            is_line_started = true;
+                        }
+
+                        doc.render_content(writer, item, &inline_options, depth);
+                    }
+                }
+
+                if is_line_end && is_line_started {
+                    options.write_newline(writer);
+                    is_line_started = false;
+                }
             }
         }
     }
@@ -110,15 +329,54 @@ pub mod html {
             return &self.elements[element_id as usize];
         }
 
-        pub fn set_attribute(&mut self, element_id: ElementID, key: &String, value: String) -> () {
-            let _ = self
-                .get_element_by_id(element_id)
-                .attributes
-                .insert(key.to_lowercase(), value);
+        pub fn is_known_tag(&self, tag_str: &str) -> bool {
+            let lower_case_tag = tag_str.to_lowercase();
+
+            return self
+                .tags
+                .binary_search_by(|x| x.start_tag.cmp(&lower_case_tag))
+                .is_ok();
+        }
+
+        /// Text, or an element laid out as `ElementLayout::Inline`.
+        pub fn is_inline_content(&self, content: &ElementContent) -> bool {
+            return match content {
+                ElementContent::Element(element_id) => {
+                    let tag_id = self.elements[*element_id as usize].tag;
+                    self.tags[tag_id as usize].layout == ElementLayout::Inline
+                }
+                ElementContent::Text(_) => true,
+            };
+        }
+
+        pub fn tag_name(&self, element_id: ElementID) -> &str {
+            let tag_id = self.elements[element_id as usize].tag;
+            return &self.tags[tag_id as usize].start_tag;
+        }
+
+        pub fn set_attribute<V: Into<AttrValue>>(
+            &mut self,
+            element_id: ElementID,
+            key: &str,
+            value: V,
+        ) -> () {
+            let key = key.to_lowercase();
+            let value = value.into();
+            let attributes = &mut self.get_element_by_id(element_id).attributes;
+
+            // NOTE(SR): Setting an existing attribute again keeps its original position.
+            match attributes.iter_mut().find(|attrib| attrib.0 == key) {
+                Some(attrib) => attrib.1 = value,
+                None => attributes.push((key, value)),
+            }
         }
 
-        pub fn remove_attribute(&mut self, element_id: ElementID, key: &String) {
-            self.get_element_by_id(element_id).attributes.remove(key);
+        pub fn remove_attribute(&mut self, element_id: ElementID, key: &str) {
+            let key = key.to_lowercase();
+
+            self.get_element_by_id(element_id)
+                .attributes
+                .retain(|attrib| attrib.0 != key);
         }
 
         pub fn insert_content_at(
@@ -157,31 +415,51 @@ pub mod html {
             match it {
                 Ok(id_index) => return id_index as ElementTagID,
                 Err(insertion_index) => {
-                    let id = self.tags.len() as ElementID;
                     self.tags.insert(
                         insertion_index,
                         ElementTag {
                             start_tag: lower_case_tag,
                             end_tag: Option::None,
                             is_void_element: false,
+                            layout: ElementLayout::Block,
                         },
                     );
-                    return id;
+
+                    // NOTE(SR): Tag ids are indices into the sorted `tags`, elements already pointing past the new one must shift along.
+                    for element in self.elements.iter_mut() {
+                        if element.tag as usize >= insertion_index {
+                            element.tag += 1;
+                        }
+                    }
+
+                    return insertion_index as ElementTagID;
                 }
             }
         }
 
-        pub fn render(&self, writer: &mut dyn Write, element_id: ElementID) {
-            self.elements[element_id as usize].render(writer, self);
+        pub fn render(
+            &self,
+            writer: &mut dyn Write,
+            element_id: ElementID,
+            options: &RenderOptions,
+        ) {
+            self.elements[element_id as usize].render(writer, self, options, 0);
         }
 
-        pub fn render_content(&self, writer: &mut dyn Write, element_content: &ElementContent) {
+        /// Writes `element_content` as if it was nested `depth` levels deep.
+        pub fn render_content(
+            &self,
+            writer: &mut dyn Write,
+            element_content: &ElementContent,
+            options: &RenderOptions,
+            depth: usize,
+        ) {
             match element_content {
                 ElementContent::Element(element_id) => {
-                    self.render(writer, *element_id);
+                    self.elements[*element_id as usize].render(writer, self, options, depth);
                 }
                 ElementContent::Text(txt) => {
-                    let _ = write!(writer, "{}", txt);
+                    options.write_text(writer, txt);
                 }
             }
         }
@@ -202,591 +480,718 @@ pub mod html {
                         start_tag: String::from("!doctype"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     // Root Element
                     ElementTag {
                         start_tag: String::from("html"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Document Metadata
                     ElementTag {
                         start_tag: String::from("base"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("head"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("link"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("meta"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("style"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("title"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Sectioning Root
                     ElementTag {
                         start_tag: String::from("body"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Content Sectioning
                     ElementTag {
                         start_tag: String::from("address"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("article"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("aside"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("footer"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("header"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h1"),
                         end_tag: Option::None,
                         is_void_element: false,
-                    },
-                    ElementTag {
-                        start_tag: String::from("h1"),
-                        end_tag: Option::None,
-                        is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h2"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h3"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h4"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h5"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("h6"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
+                    },
+                    ElementTag {
+                        start_tag: String::from("main"),
+                        end_tag: Option::None,
+                        is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("nav"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("hgroup"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("section"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("search"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Text Content
                     ElementTag {
                         start_tag: String::from("blockquote"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("dd"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("div"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
+                    },
+                    ElementTag {
+                        start_tag: String::from("dl"),
+                        end_tag: Option::None,
+                        is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("dt"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("figcaption"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("figure"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("hr"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("li"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("menu"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("ol"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("p"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("pre"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Preformatted,
                     },
                     ElementTag {
                         start_tag: String::from("ul"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Inline Text Semantics
                     ElementTag {
                         start_tag: String::from("a"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("abbr"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("b"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("bdi"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("bdo"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("br"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("cite"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("code"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("data"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("dfn"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("em"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("i"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("kbd"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("mark"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("q"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("rp"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("rt"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("ruby"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("s"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("samp"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("small"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("span"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("strong"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("sub"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("sup"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("time"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("u"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("var"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("wbr"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Inline,
                     },
                     // Image and Multimedia
                     ElementTag {
                         start_tag: String::from("area"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("audio"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("img"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("map"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("track"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("video"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Embedded Content
                     ElementTag {
                         start_tag: String::from("embed"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("iframe"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("object"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
+                    },
+                    ElementTag {
+                        start_tag: String::from("param"),
+                        end_tag: Option::None,
+                        is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("picture"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("portal"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("source"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     // SVG and MathML
                     ElementTag {
                         start_tag: String::from("svg"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("math"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Scripting
                     ElementTag {
                         start_tag: String::from("canvas"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("noscript"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("script"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Demarcating Edits
                     ElementTag {
                         start_tag: String::from("del"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("ins"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     // Table Content
                     ElementTag {
                         start_tag: String::from("caption"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("col"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("colgroup"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("table"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("tbody"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("td"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("tfoot"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("th"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("thead"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("tr"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Forms
                     ElementTag {
                         start_tag: String::from("button"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("datalist"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("fieldset"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("form"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("input"),
                         end_tag: Option::None,
                         is_void_element: true,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("label"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Inline,
                     },
                     ElementTag {
                         start_tag: String::from("legend"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("meter"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("optgroup"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("option"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("output"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("progress"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("select"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("textarea"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Preformatted,
                     },
                     // Interactive Elements
                     ElementTag {
                         start_tag: String::from("details"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("dialog"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("summary"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     // Web Components
                     ElementTag {
                         start_tag: String::from("slot"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                     ElementTag {
                         start_tag: String::from("template"),
                         end_tag: Option::None,
                         is_void_element: false,
+                        layout: ElementLayout::Block,
                     },
                 ],
                 doc_type: Default::default(),
@@ -801,15 +1206,24 @@ pub mod html {
                     a.start_tag.partial_cmp(&b.start_tag).unwrap()
                 });
 
+            // NOTE(SR): `tag_id_from_string` binary searches the table, a duplicate would make lookups ambiguous.
+            debug_assert!(
+                result
+                    .tags
+                    .windows(2)
+                    .all(|pair| pair[0].start_tag < pair[1].start_tag),
+                "The built-in tag table has a duplicate entry."
+            );
+
             result.doc_type = result.create_element("!doctype");
             result.html = result.create_element("html");
             result.head = result.create_element("head");
             result.body = result.create_element("body");
 
-            result.set_attribute(result.doc_type, &"html".to_string(), "".to_string());
+            result.set_attribute(result.doc_type, "html", AttrValue::Boolean);
             result.insert_content_at(result.doc_type, 0, ElementContent::Element(result.html));
 
-            result.set_attribute(result.html, &"lang".to_string(), "en".to_string());
+            result.set_attribute(result.html, "lang", "en".to_string());
             result.insert_content_at(result.html, 0, ElementContent::Element(result.head));
             result.insert_content_at(result.html, 1, ElementContent::Element(result.body));
 
@@ -821,19 +1235,15 @@ pub mod html {
             );
             let meta_xua = result.create_element("meta");
 
-            result.set_attribute(meta_charset, &"charset".to_string(), "utf-8".to_string());
-            result.set_attribute(meta_viewport, &"name".to_string(), "viewport".to_string());
+            result.set_attribute(meta_charset, "charset", "utf-8".to_string());
+            result.set_attribute(meta_viewport, "name", "viewport".to_string());
             result.set_attribute(
                 meta_viewport,
-                &"content".to_string(),
+                "content",
                 "width=device-width, initial-scale=1.0".to_string(),
             );
-            result.set_attribute(
-                meta_xua,
-                &"http-equiv".to_string(),
-                "X-UA-Compatible".to_string(),
-            );
-            result.set_attribute(meta_xua, &"content".to_string(), "ie=edge".to_string());
+            result.set_attribute(meta_xua, "http-equiv", "X-UA-Compatible".to_string());
+            result.set_attribute(meta_xua, "content", "ie=edge".to_string());
 
             result.push_content(result.head, ElementContent::Element(meta_charset));
             result.push_content(result.head, ElementContent::Element(meta_viewport));
@@ -843,11 +1253,22 @@ pub mod html {
         }
     }
 
+    /// An edit of the finished document run right before it is rendered (add a footer, rewrite links, ...).
+    pub trait DocumentTransform {
+        fn apply(&mut self, doc: &mut Document);
+    }
+
+    impl<F: FnMut(&mut Document)> DocumentTransform for F {
+        fn apply(&mut self, doc: &mut Document) {
+            self(doc);
+        }
+    }
+
     pub fn create_meta_tag(doc: &mut Document, name: String, content: String) -> ElementID {
         let result = doc.create_element("meta");
 
-        doc.set_attribute(result, &"name".to_string(), name);
-        doc.set_attribute(result, &"content".to_string(), content);
+        doc.set_attribute(result, "name", name);
+        doc.set_attribute(result, "content", content);
 
         return result;
     }
@@ -860,16 +1281,363 @@ pub mod html {
             .replace("\"", "&quot;")
             .replace("'", "&#039;");
     }
+
+    /// Percent-encodes the path portion of a url, the scheme, host, query and fragment are left as is.
+    /// Already encoded `%XX` sequences are kept so encoding twice is harmless.
+    pub fn encode_url_path(url: &str) -> String {
+        let path_start = match url.find("://") {
+            Some(scheme_end) => url[scheme_end + 3..]
+                .find('/')
+                .map_or(url.len(), |i| scheme_end + 3 + i),
+            None => 0,
+        };
+        let path_end = url[path_start..]
+            .find(['?', '#'])
+            .map_or(url.len(), |i| path_start + i);
+
+        let path = &url.as_bytes()[path_start..path_end];
+        let mut result = String::from(&url[..path_start]);
+        let mut i = 0;
+
+        while i < path.len() {
+            let c = path[i];
+            let is_encoded_sequence = c == b'%'
+                && i + 2 < path.len()
+                && path[i + 1].is_ascii_hexdigit()
+                && path[i + 2].is_ascii_hexdigit();
+
+            if c.is_ascii_alphanumeric()
+                || b"-._~/:@!$&'()*+,;=".contains(&c)
+                || is_encoded_sequence
+            {
+                result.push(c as char);
+            } else {
+                result.push_str(&format!("%{:02X}", c));
+            }
+
+            i += 1;
+        }
+
+        result.push_str(&url[path_end..]);
+
+        return result;
+    }
 }
 
 #[derive(Debug, StructOpt)]
 struct Options {
+    /// Document to convert, `-` reads it from standard input.
     #[structopt(long, default_value = "TestInput.srmark")]
     pub input: String,
+
+    /// File to write the html to (parent directories are created), standard out if not given.
+    #[structopt(short, long)]
+    pub output: Option<String>,
+
+    /// Treat unknown tags, links to undefined ids and missing required attributes as errors.
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Percent-encode the path of `Src` urls (spaces, unicode, etc).
+    #[structopt(long)]
+    pub encode_urls: bool,
+
+    /// Wrap number / bool literals in the body with a `<span class="literal-number|literal-bool">`.
+    #[structopt(long)]
+    pub typed_literals: bool,
+
+    /// Sort the classes of each `class` attribute (duplicates are always removed).
+    #[structopt(long)]
+    pub sort_classes: bool,
+
+    /// Maximum nesting of `@file` includes.
+    #[structopt(long, default_value = "16")]
+    pub max_include_depth: u32,
+
+    /// Maximum number of `@file` includes in a whole document.
+    #[structopt(long, default_value = "256")]
+    pub max_includes: u32,
+
+    /// Directory `@file` includes must be inside of, defaults to the input document's directory.
+    #[structopt(long)]
+    pub include_root: Option<String>,
+
+    /// Newline style of the generated html, either `lf` or `crlf`.
+    #[structopt(long, default_value = "lf", possible_values = &["lf", "crlf"])]
+    pub line_ending: String,
+
+    /// Text of a `<footer>` appended to the end of the body.
+    #[structopt(long)]
+    pub footer: Option<String>,
+
+    /// Render the document without whitespace between tags and with runs of spaces in text collapsed,
+    /// the template itself is written as is.
+    #[structopt(long)]
+    pub minify: bool,
+
+    /// Write void elements XHTML style, `<br />` rather than `<br>`.
+    #[structopt(long)]
+    pub self_close_void: bool,
+
+    /// Build a table of contents from the headings for the template's `toc` variable.
+    #[structopt(long)]
+    pub toc: bool,
+
+    /// Extra attributes a tag must have (`--require-attribute image=Alt`), on top of `Src` for
+    /// images / links etc. Missing ones are warnings, errors with `--strict`.
+    #[structopt(long = "require-attribute")]
+    pub required_attributes: Vec<String>,
+
+    /// Keep number literals as they were written (`16.0` stays `16.0` rather than becoming `16`).
+    #[structopt(long)]
+    pub preserve_number_text: bool,
+
+    /// Drop the spaces between inline tags and the text around them, `@b{un} believable` renders as
+    /// `<b>un</b>believable`. By default they are kept so the html reads like the source.
+    #[structopt(long)]
+    pub trim_tag_spacing: bool,
+
+    /// Extra `srmark=html` tag mappings (`--map-tag quote=blockquote`), overriding the defaults.
+    #[structopt(long = "map-tag")]
+    pub tag_mappings: Vec<String>,
+}
+
+/// Lower case words of `text` joined by '-', anything other than letters, digits, '-' and '_' is dropped
+/// along with words left without a letter or digit (`"- What's New? -"` becomes `whats-new`).
+fn slugify(text: &str) -> String {
+    return text
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .collect::<Vec<String>>()
+        .join("-");
+}
+
+/// Rewrites every newline in `text` (including ones already `\r\n` from the template) as `line_ending`.
+fn with_line_endings(text: &str, line_ending: &str) -> String {
+    let normalized = text.replace("\r\n", "\n");
+
+    if line_ending == "\n" {
+        return normalized;
+    }
+
+    return normalized.replace('\n', line_ending);
+}
+
+/// How many `@define` macros may expand inside of each other before assuming it is recursive.
+const MAX_MACRO_DEPTH: u32 = 32;
+
+/// Hard caps on `@file` includes so untrusted input cannot include-bomb the generator.
+struct IncludeLimits {
+    max_depth: u32,
+    max_total: u32,
+}
+
+impl Default for IncludeLimits {
+    fn default() -> Self {
+        IncludeLimits {
+            max_depth: 16,
+            max_total: 256,
+        }
+    }
+}
+
+/// Problems found while generating the html, reported after the whole document is visited.
+enum GenError {
+    FileLoad {
+        path: String,
+        reason: String,
+    },
+    UnknownTag(String),
+    UndefinedRef(String),
+    IncludeDepthExceeded {
+        path: String,
+        max_depth: u32,
+    },
+    IncludeCountExceeded {
+        path: String,
+        max_total: u32,
+    },
+    IncludeOutsideRoot {
+        path: String,
+        root: String,
+    },
+    IncludeCycle(String),
+    IncludeParse {
+        path: String,
+        line_number: usize,
+        column_number: usize,
+        message: String,
+    },
+    AttributeType {
+        tag: String,
+        key: String,
+        expected: AttributeType,
+    },
+    MissingAttribute(srmarkup::MissingAttribute),
+    CellPlacement {
+        tag: String,
+        parent: String,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum AttributeType {
+    Number,
+    Bool,
+}
+
+impl AttributeType {
+    /// The type an attribute's value must have, `None` if any literal is fine.
+    fn expected_for(key: &str) -> Option<AttributeType> {
+        return match key.to_lowercase().as_str() {
+            "width" | "height" | "colspan" | "rowspan" | "tabindex" => Some(AttributeType::Number),
+            "disabled" | "autoplay" | "loop" | "muted" | "hidden" | "checked" => {
+                Some(AttributeType::Bool)
+            }
+            _ => None,
+        };
+    }
+
+    fn matches(&self, value: &srmarkup::ASTNodeLiteral) -> bool {
+        return match (self, value) {
+            (AttributeType::Number, srmarkup::ASTNodeLiteral::Float { .. }) => true,
+            (AttributeType::Number, srmarkup::ASTNodeLiteral::Measure { .. }) => true,
+            (AttributeType::Number, srmarkup::ASTNodeLiteral::Str(str)) => {
+                str.trim().parse::<f64>().is_ok()
+            }
+            (AttributeType::Bool, srmarkup::ASTNodeLiteral::Bool(_)) => true,
+            (AttributeType::Bool, srmarkup::ASTNodeLiteral::Str(str)) => {
+                str == "true" || str == "false"
+            }
+            _ => false,
+        };
+    }
+}
+
+impl std::fmt::Display for AttributeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttributeType::Number => write!(f, "number"),
+            AttributeType::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+impl std::fmt::Display for GenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenError::FileLoad { path, reason } => {
+                write!(f, "Failed to load file ('{}'), {}.", path, reason)
+            }
+            GenError::UnknownTag(tag) => write!(f, "Unknown tag '@{}'.", tag),
+            GenError::UndefinedRef(id) => write!(f, "Link to undefined id '#{}'.", id),
+            GenError::IncludeDepthExceeded { path, max_depth } => write!(
+                f,
+                "Including file ('{}') exceeds the maximum include depth of {}.",
+                path, max_depth
+            ),
+            GenError::IncludeCountExceeded { path, max_total } => write!(
+                f,
+                "Including file ('{}') exceeds the maximum of {} included files.",
+                path, max_total
+            ),
+            GenError::IncludeOutsideRoot { path, root } => write!(
+                f,
+                "Included file ('{}') is outside of the include root ('{}').",
+                path, root
+            ),
+            GenError::IncludeCycle(path) => {
+                write!(f, "File ('{}') includes itself.", path)
+            }
+            GenError::IncludeParse {
+                path,
+                line_number,
+                column_number,
+                message,
+            } => write!(
+                f,
+                "{}: Line({}), Column({}): {}",
+                path, line_number, column_number, message
+            ),
+            GenError::AttributeType { tag, key, expected } => write!(
+                f,
+                "'@{}' attribute '{}' should be a {} value.",
+                tag, key, expected
+            ),
+            GenError::MissingAttribute(missing) => write!(
+                f,
+                "Line({}): '@{}' is missing the required attribute '{}'.",
+                missing.line_number, missing.tag, missing.attribute
+            ),
+            GenError::CellPlacement { tag, parent } => write!(
+                f,
+                "'@{}' must be placed inside of a '@tr' but was found in '{}'.",
+                tag, parent
+            ),
+        }
+    }
+}
+
+/// Parses the main document and `@file(Parse=true)` includes.
+///
+/// `keep_tag_spacing` keeps the whitespace before and after inline tags as text, without it
+/// `Hello @b{world} again` loses both spaces around `<b>` once rendered (`--trim-tag-spacing` turns it off).
+fn parse_document(
+    source: String,
+    preserve_number_text: bool,
+    keep_tag_spacing: bool,
+) -> srmarkup::ParseResult {
+    let mut parser = srmarkup::Parser::new(source);
+    parser.preserve_number_text = preserve_number_text;
+    // NOTE(SR): Code samples keep their indentation and line breaks.
+    parser.verbatim_tags = vec!["code".to_string()];
+    parser.keep_space_before_tag = keep_tag_spacing;
+    parser.keep_space_after_tag = keep_tag_spacing;
+    // NOTE(SR): `@define` bodies use `${param}`, they are expanded by `expand_macros` in `main`.
+    parser.macro_placeholders = true;
+
+    return parser.parse();
+}
+
+/// The contents of `doc`'s `<body>`, without the body tag itself.
+fn render_body(doc: &html::Document, options: &html::RenderOptions) -> String {
+    let mut body_content = std::io::BufWriter::new(Vec::new());
+
+    doc.get_const_element_by_id(doc.body)
+        .render_contents(&mut body_content, doc, options, 0);
+
+    return String::from_utf8(body_content.into_inner().unwrap()).unwrap();
+}
+
+/// Writes `contents` to `file_name`, creating any missing parent directories.
+fn write_entire_file(file_name: &str, contents: &str) -> std::io::Result<()> {
+    if let Some(parent_dir) = std::path::Path::new(file_name).parent() {
+        std::fs::create_dir_all(parent_dir)?;
+    }
+
+    return std::fs::write(file_name, contents);
 }
 
+/// The `--input` that reads the document from standard input.
+const STDIN_INPUT: &str = "-";
+
 fn load_entire_file(file_name: &str) -> String {
     let mut result = String::new();
+
+    if file_name == STDIN_INPUT {
+        if let Err(msg) = std::io::stdin().read_to_string(&mut result) {
+            eprintln!("[ERROR] Failed to read standard input, {}.", msg);
+        }
+
+        return result;
+    }
+
     let file = File::open(file_name);
 
     match file {
@@ -895,11 +1663,19 @@ fn main() {
     let options: Options = Options::from_args();
     let input_path = &options.input;
     let input_source = load_entire_file(input_path);
-    let mut parser = srmarkup::Parser::new(input_source);
-    let parse_result: srmarkup::ParseResult = parser.parse();
+    let parse_result: srmarkup::ParseResult = parse_document(
+        input_source.clone(),
+        options.preserve_number_text,
+        !options.trim_tag_spacing,
+    );
 
     match parse_result {
-        Ok(root_node) => {
+        Ok(mut root_node) => {
+            if let Err(err) = srmarkup::expand_macros(&mut root_node, MAX_MACRO_DEPTH) {
+                eprintln!("Macro Error:\n  {}", err);
+                std::process::exit(1);
+            }
+
             // title
             // theme
             // background_image
@@ -907,17 +1683,126 @@ fn main() {
             // post_content
             let blog_post_template = load_entire_file("blog_post_template.html");
             let mut processor: HTMLProcessor = HTMLProcessor::new();
-            srmarkup::visit_ast(&root_node, &mut processor);
+            processor.strict = options.strict;
+            processor.encode_urls = options.encode_urls;
+            processor.typed_literal_spans = options.typed_literals;
+            processor.sort_classes = options.sort_classes;
+            processor.preserve_number_text = options.preserve_number_text;
+            processor.keep_tag_spacing = !options.trim_tag_spacing;
+
+            for mapping in &options.tag_mappings {
+                match mapping.split_once('=') {
+                    Some((from, to)) => {
+                        processor.map_tag(from.trim(), to.trim());
+                    }
+                    None => {
+                        eprintln!(
+                            "[ERROR] Tag mapping '{}' is not of the form `from=to`.",
+                            mapping
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+            let mut required_attributes = srmarkup::RequiredAttributes::srmark_defaults();
+
+            for requirement in &options.required_attributes {
+                match requirement.split_once('=') {
+                    Some((tag, attribute)) => {
+                        required_attributes.require(tag.trim(), attribute.trim());
+                    }
+                    None => {
+                        eprintln!(
+                            "[ERROR] Required attribute '{}' is not of the form `tag=attribute`.",
+                            requirement
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            for missing in srmarkup::check_required_attributes(&root_node, &required_attributes) {
+                if processor.strict {
+                    processor.errors.push(GenError::MissingAttribute(missing));
+                } else {
+                    processor.warnings.push(GenError::MissingAttribute(missing));
+                }
+            }
+
+            processor.include_limits = IncludeLimits {
+                max_depth: options.max_include_depth,
+                max_total: options.max_includes,
+            };
+
+            if let Some(footer_text) = options.footer.clone() {
+                processor
+                    .transforms
+                    .push(Box::new(move |doc: &mut html::Document| {
+                        let footer = doc.create_element("footer");
+                        doc.push_content(
+                            footer,
+                            html::ElementContent::Text(html::escape(&footer_text)),
+                        );
+                        doc.push_content(doc.body, html::ElementContent::Element(footer));
+                    }));
+            }
+
+            let document_path = std::path::Path::new(input_path);
+            let context = srmarkup::ProcessorContext {
+                // NOTE(SR): `parent` of a bare file name is "", which would not canonicalize.
+                base_path: document_path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .unwrap_or(std::path::Path::new("."))
+                    .to_path_buf(),
+                ..Default::default()
+            };
+
+            processor.include_root = options.include_root.as_ref().map(PathBuf::from);
+            // NOTE(SR): A document from standard input has no path, includes are relative to the working directory.
+            if input_path != STDIN_INPUT {
+                processor
+                    .include_stack
+                    .extend(document_path.canonicalize().ok());
+            }
+
+            srmarkup::visit_ast_with_context(&root_node, &mut processor, &context);
+
+            for warning in &processor.warnings {
+                eprintln!("[WARNING] {}", warning);
+            }
+
+            if !processor.errors.is_empty() {
+                eprintln!("Generation Error:");
+                for err in &processor.errors {
+                    eprintln!("  {}", err);
+                }
+                std::process::exit(1);
+            }
+
+            processor.apply_transforms();
+
+            let table_of_contents = if options.toc {
+                processor.build_table_of_contents()
+            } else {
+                None
+            };
 
             let doc = &processor.doc;
-            let mut post_content = std::io::BufWriter::new(Vec::new());
+            let mut render_options = if options.minify {
+                html::RenderOptions::minified()
+            } else {
+                html::RenderOptions::default()
+            };
+            render_options.self_close_void = options.self_close_void;
+            let mut toc_content = std::io::BufWriter::new(Vec::new());
 
-            for body_content in doc.get_const_element_by_id(doc.body).contents.iter() {
-                doc.render_content(&mut post_content, body_content);
+            if let Some(toc_list) = table_of_contents {
+                doc.render(&mut toc_content, toc_list, &render_options);
             }
 
-            let bytes = post_content.into_inner().unwrap();
-            let post_content = String::from_utf8(bytes).unwrap();
+            let post_content = render_body(doc, &render_options);
+            let toc_content = String::from_utf8(toc_content.into_inner().unwrap()).unwrap();
 
             let ctx_vars = context! {
               title => processor.title,
@@ -925,6 +1810,7 @@ fn main() {
               background_image => processor.cover_image,
               date => processor.date,
               post_content => post_content,
+              toc => toc_content,
             };
 
             let mut env = Environment::new();
@@ -933,17 +1819,39 @@ fn main() {
 
             let main_template = env.get_template("blog_post_template.html").unwrap();
 
-            println!("{}", main_template.render(ctx_vars).unwrap());
+            let line_ending = if options.line_ending == "crlf" {
+                "\r\n"
+            } else {
+                "\n"
+            };
+            let html_output = main_template.render(ctx_vars).unwrap() + "\n";
+
+            let html_output = with_line_endings(&html_output, line_ending);
+
+            match &options.output {
+                Some(output_path) => {
+                    if let Err(msg) = write_entire_file(output_path, &html_output) {
+                        eprintln!("[ERROR] Failed to write file ('{}'), {}.", output_path, msg);
+                        std::process::exit(1);
+                    }
+                }
+                None => print!("{}", html_output),
+            }
         }
         Err(error_log) => {
-            eprintln!("Parse Error:");
-            for err in &error_log.errors {
-                eprintln!("  Line({}): {}\n", err.line_number, err.message);
-            }
+            eprint!("{}", error_log.render(&input_source));
+            std::process::exit(1);
         }
     }
 }
 
+/// A heading seen while visiting the document, for the table of contents.
+struct HeadingEntry {
+    level: u8,
+    id: String,
+    text: String,
+}
+
 struct HTMLProcessor {
     doc: html::Document,
     title: String,
@@ -951,6 +1859,25 @@ struct HTMLProcessor {
     date: String,
     theme: String,
     element_stack: Vec<html::ElementID>,
+    strict: bool,
+    encode_urls: bool,
+    typed_literal_spans: bool,
+    sort_classes: bool,
+    preserve_number_text: bool, // Also used when parsing `@file(Parse=true)` includes.
+    keep_tag_spacing: bool,     // Also used for includes, see `parse_document`.
+    errors: Vec<GenError>,
+    warnings: Vec<GenError>,
+    defined_ids: std::collections::HashSet<String>,
+    referenced_ids: Vec<String>,
+    include_limits: IncludeLimits,
+    include_depth: u32,
+    include_count: u32,
+    include_root: Option<PathBuf>, // Includes must be inside of it, the document's directory if not set.
+    include_stack: Vec<PathBuf>,   // Canonical paths of the document and the files being included.
+    base_path: PathBuf,            // Directory of the document, from the `ProcessorContext`.
+    transforms: Vec<Box<dyn html::DocumentTransform>>, // Run in order by `apply_transforms`.
+    tag_remap: HashMap<String, String>, // Lower case srmark tag to html element, see `map_tag`.
+    headings: Vec<HeadingEntry>,   // In document order.
 }
 
 impl HTMLProcessor {
@@ -962,6 +1889,41 @@ impl HTMLProcessor {
             date: Default::default(),
             theme: Default::default(),
             element_stack: vec![],
+            strict: false,
+            encode_urls: false,
+            typed_literal_spans: false,
+            sort_classes: false,
+            preserve_number_text: false,
+            keep_tag_spacing: true,
+            errors: vec![],
+            warnings: vec![],
+            defined_ids: Default::default(),
+            referenced_ids: vec![],
+            include_limits: Default::default(),
+            include_depth: 0,
+            include_count: 0,
+            include_root: None,
+            include_stack: vec![],
+            base_path: PathBuf::new(),
+            transforms: vec![],
+            headings: vec![],
+            tag_remap: HashMap::from(
+                [
+                    ("text", "p"),
+                    ("image", "img"),
+                    ("link", "a"),
+                    ("ulist", "ul"),
+                    ("olist", "ol"),
+                    ("listitem", "li"),
+                ]
+                .map(|(from, to)| (from.to_string(), to.to_string())),
+            ),
+        }
+    }
+
+    fn apply_transforms(self: &mut Self) {
+        for transform in self.transforms.iter_mut() {
+            transform.apply(&mut self.doc);
         }
     }
 
@@ -973,40 +1935,313 @@ impl HTMLProcessor {
         self.element_stack.pop();
     }
 
-    fn remap_tag(tag: &str) -> &str {
-        return match tag {
-            "text" => "p",
-            "image" => "img",
-            "link" => "a",
-            "ulist" => "ul",
-            "olist" => "ol",
-            "listitem" => "li",
-            _ => tag,
-        };
-    }
+    fn include_file(&mut self, tag_node: &srmarkup::ASTNodeTag, depth: usize) {
+        let file_path = tag_node.find_attribute_ci("Source");
 
-    fn find_attribute_str(tag_node: &srmarkup::ASTNodeTag, key: &str) -> String {
-        let attrib = tag_node.find_attribute(key);
+        if file_path.is_none() {
+            return;
+        }
+
+        let file_path_string = file_path.unwrap().to_string();
+
+        if self.include_depth >= self.include_limits.max_depth {
+            self.errors.push(GenError::IncludeDepthExceeded {
+                path: file_path_string,
+                max_depth: self.include_limits.max_depth,
+            });
+            return;
+        }
+
+        if self.include_count >= self.include_limits.max_total {
+            self.errors.push(GenError::IncludeCountExceeded {
+                path: file_path_string,
+                max_total: self.include_limits.max_total,
+            });
+            return;
+        }
+
+        // NOTE(SR): Relative to the file containing the `@file`, not the working directory.
+        let including_dir = match self.include_stack.last() {
+            Some(including_file) => including_file.parent().unwrap_or(&self.base_path),
+            None => &self.base_path,
+        };
+        let resolved_path = match including_dir.join(&file_path_string).canonicalize() {
+            Ok(resolved_path) => resolved_path,
+            Err(msg) => {
+                self.errors.push(GenError::FileLoad {
+                    path: file_path_string,
+                    reason: msg.to_string(),
+                });
+                return;
+            }
+        };
+        let include_root = self.include_root.as_ref().unwrap_or(&self.base_path);
+        let include_root = match include_root.canonicalize() {
+            Ok(include_root) => include_root,
+            Err(msg) => {
+                self.errors.push(GenError::FileLoad {
+                    path: include_root.display().to_string(),
+                    reason: msg.to_string(),
+                });
+                return;
+            }
+        };
+
+        if !resolved_path.starts_with(&include_root) {
+            self.errors.push(GenError::IncludeOutsideRoot {
+                path: file_path_string,
+                root: include_root.display().to_string(),
+            });
+            return;
+        }
+
+        if self.include_stack.contains(&resolved_path) {
+            self.errors.push(GenError::IncludeCycle(file_path_string));
+            return;
+        }
+
+        self.include_count += 1;
+        self.include_depth += 1;
+        self.include_stack.push(resolved_path.clone());
+
+        let file = File::open(&resolved_path);
+
+        match file {
+            Ok(mut file) => {
+                let mut source = String::new();
+                let source_size = file.read_to_string(&mut source);
+
+                match source_size {
+                    Ok(_)
+                        if matches!(
+                            tag_node.find_attribute_ci("Parse"),
+                            Some(srmarkup::ASTNodeLiteral::Bool(true))
+                        ) =>
+                    {
+                        self.include_parsed_file(&file_path_string, source);
+                    }
+                    Ok(_) => {
+                        self.visit_text(&ASTNodeText::new(source, tag_node.span), depth + 1);
+                    }
+                    Err(msg) => {
+                        self.errors.push(GenError::FileLoad {
+                            path: file_path_string,
+                            reason: msg.to_string(),
+                        });
+                    }
+                }
+            }
+            Err(msg) => {
+                self.errors.push(GenError::FileLoad {
+                    path: file_path_string,
+                    reason: msg.to_string(),
+                });
+            }
+        }
+
+        self.include_stack.pop();
+        self.include_depth -= 1;
+    }
+
+    /// Visits the document in `source` as if it was written in place of the `@file`.
+    fn include_parsed_file(&mut self, file_path: &str, source: String) {
+        match parse_document(source, self.preserve_number_text, self.keep_tag_spacing) {
+            Ok(included_root) => {
+                if let srmarkup::ASTNode::Root(included_root) = included_root.as_ref() {
+                    for child in included_root.children.iter() {
+                        srmarkup::visit_ast(child, self);
+                    }
+                }
+            }
+            Err(error_log) => {
+                for err in error_log.errors {
+                    self.errors.push(GenError::IncludeParse {
+                        path: file_path.to_string(),
+                        line_number: err.line_number,
+                        column_number: err.column_number,
+                        message: err.message,
+                    });
+                }
+            }
+        }
+    }
+
+    fn check_attribute_types(&mut self, tag_node: &srmarkup::ASTNodeTag) {
+        for (key, value) in tag_node.attributes.iter() {
+            if let Some(expected) = AttributeType::expected_for(key) {
+                if !expected.matches(value) {
+                    self.warnings.push(GenError::AttributeType {
+                        tag: tag_node.text.clone(),
+                        key: key.clone(),
+                        expected,
+                    });
+                }
+            }
+        }
+    }
+
+    fn implicit_table_body(&mut self, table: html::ElementID) -> html::ElementID {
+        let last_content = self.doc.get_const_element_by_id(table).contents.last();
 
-        if attrib.is_some() {
-            return attrib.unwrap().to_string();
+        if let Some(html::ElementContent::Element(last_element)) = last_content {
+            if self.doc.tag_name(*last_element) == "tbody" {
+                return *last_element;
+            }
+        }
+
+        let tbody = self.doc.create_element("tbody");
+        self.doc
+            .push_content(table, html::ElementContent::Element(tbody));
+
+        return tbody;
+    }
+
+    /// Emit `@from` as a `<to>` element, replaces any earlier mapping of `from` (including the defaults).
+    pub fn map_tag(self: &mut Self, from: &str, to: &str) -> &mut Self {
+        self.tag_remap
+            .insert(from.to_lowercase(), to.to_lowercase());
+        return self;
+    }
+
+    // NOTE(SR): An exact lookup, `visit_begin_tag` has already lower cased the tag name
+    //           so `@Text` and `@text` map the same way, anything not in the table passes through.
+    fn remap_tag<'a>(self: &'a Self, tag: &'a str) -> &'a str {
+        return self.tag_remap.get(tag).map_or(tag, String::as_str);
+    }
+
+    /// Nested `<ul>` of links to every heading, a heading deeper than the one before it starts
+    /// a list inside of that one's `<li>`. `None` if the document has no headings.
+    ///
+    /// The list is not added to the document, render it with `html::Document::render`.
+    fn build_table_of_contents(self: &mut Self) -> Option<html::ElementID> {
+        let first_heading = self.headings.first()?;
+        let root_list = self.doc.create_element("ul");
+
+        // (list, level of the headings in it, last item added to it)
+        let mut list_stack: Vec<(html::ElementID, u8, Option<html::ElementID>)> =
+            vec![(root_list, first_heading.level, None)];
+
+        for heading in self.headings.iter() {
+            while list_stack.len() > 1 && heading.level < list_stack.last().unwrap().1 {
+                list_stack.pop();
+            }
+
+            let (_, current_level, last_item) = *list_stack.last().unwrap();
+
+            if let Some(last_item) = last_item.filter(|_| heading.level > current_level) {
+                let nested_list = self.doc.create_element("ul");
+                self.doc
+                    .push_content(last_item, html::ElementContent::Element(nested_list));
+                list_stack.push((nested_list, heading.level, None));
+            }
+
+            let list = list_stack.last().unwrap().0;
+            let item = self.doc.create_element("li");
+            let link = self.doc.create_element("a");
+
+            self.doc
+                .set_attribute(link, "href", format!("#{}", heading.id));
+            self.doc.push_content(
+                link,
+                html::ElementContent::Text(html::escape(&heading.text)),
+            );
+            self.doc
+                .push_content(item, html::ElementContent::Element(link));
+            self.doc
+                .push_content(list, html::ElementContent::Element(item));
+
+            list_stack.last_mut().unwrap().2 = Some(item);
+        }
+
+        return Some(root_list);
+    }
+
+    fn heading_level(html_tag: &str) -> Option<u8> {
+        return match srmarkup::classify_tag(html_tag) {
+            Some(srmarkup::SemanticTag::Heading(level)) => Some(level),
+            _ => None,
+        };
+    }
+
+    /// All of the text in the heading as written, on one line.
+    fn heading_text(tag_node: &srmarkup::ASTNodeTag) -> String {
+        let mut heading_text = String::new();
+        HTMLProcessor::collect_verbatim_text(&tag_node.children, &mut heading_text);
+
+        return heading_text
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+    }
+
+    /// Slug of the heading's text, with `-1`, `-2`, ... appended if that id is already taken.
+    /// `None` for a heading without any text.
+    fn unique_heading_id(self: &Self, heading_text: &str) -> Option<String> {
+        let slug = slugify(heading_text);
+
+        if slug.is_empty() {
+            return None;
+        }
+
+        let mut id_string = slug.clone();
+        let mut duplicate_count = 0;
+
+        while self.defined_ids.contains(&id_string) {
+            duplicate_count += 1;
+            id_string = format!("{}-{}", slug, duplicate_count);
+        }
+
+        return Some(id_string);
+    }
+
+    /// Text of the children exactly as written, nested tags contribute just their text.
+    fn collect_verbatim_text(children: &srmarkup::ASTNodeList, result: &mut String) {
+        for child in children {
+            match child.as_ref() {
+                srmarkup::ASTNode::Tag(tag_node) => {
+                    HTMLProcessor::collect_verbatim_text(&tag_node.children, result)
+                }
+                srmarkup::ASTNode::Text(text_node) => result.push_str(&text_node.text),
+                srmarkup::ASTNode::Literal(literal_node) => {
+                    result.push_str(&literal_node.to_string())
+                }
+                srmarkup::ASTNode::Root(_) => {}
+            }
+        }
+    }
+
+    fn find_attribute_str(tag_node: &srmarkup::ASTNodeTag, key: &str) -> String {
+        if let Some(attrib) = tag_node.find_attribute_ci(key) {
+            return attrib.to_string();
         }
 
         return "".to_string();
     }
 
-    fn extract_classes(tag_node: &srmarkup::ASTNodeTag) -> String {
-        let css_class = tag_node.find_attribute("Class");
-        let css_size = tag_node.find_attribute("Size");
+    /// The `Class` attribute (a string or a list of them) along with the classes implied by `Size`,
+    /// duplicates removed and sorted when `sort_classes` is set.
+    fn extract_classes(self: &Self, tag_node: &srmarkup::ASTNodeTag) -> String {
+        let css_class = tag_node.find_attribute_ci("Class");
+        let css_size = match tag_node.find_attribute_ci("Size") {
+            Some(srmarkup::ASTNodeLiteral::Str(css_size)) => Some(css_size),
+            _ => None,
+        };
 
-        let mut result: String = Default::default();
+        let mut class_text: String = Default::default();
 
-        if css_class.is_some() {
-            result.push_str(&css_class.unwrap().to_string());
+        match css_class {
+            Some(srmarkup::ASTNodeLiteral::List(items)) => {
+                for item in items {
+                    class_text.push(' ');
+                    class_text.push_str(&item.to_string());
+                }
+            }
+            Some(css_class) => class_text.push_str(&css_class.to_string()),
+            None => {}
         }
 
-        if css_size.is_some() {
-            let css_size = css_size.unwrap().to_string().to_lowercase();
+        if let Some(css_size) = css_size {
+            let css_size = css_size.to_lowercase();
 
             let append = match css_size.as_str() {
                 "full" => " post-full",
@@ -1014,17 +2249,35 @@ impl HTMLProcessor {
                 _ => "",
             };
 
-            result.push_str(append);
+            class_text.push_str(append);
         }
 
-        result.push_str("");
+        let mut classes: Vec<&str> = Vec::new();
 
-        result
+        for class in class_text.split_whitespace() {
+            if !classes.contains(&class) {
+                classes.push(class);
+            }
+        }
+
+        if self.sort_classes {
+            classes.sort_unstable();
+        }
+
+        return classes.join(" ");
     }
 }
 
 impl srmarkup::IASTProcessor for HTMLProcessor {
-    fn visit_begin_root(&mut self, _: &srmarkup::ASTNodeRoot) -> srmarkup::ASTProcessorVisitResult {
+    fn set_context(&mut self, context: &srmarkup::ProcessorContext) {
+        self.base_path = context.base_path.clone();
+    }
+
+    fn visit_begin_root(
+        &mut self,
+        _: &srmarkup::ASTNodeRoot,
+        _depth: usize,
+    ) -> srmarkup::ASTProcessorVisitResult {
         self.push_element(self.doc.body);
         return srmarkup::ASTProcessorVisitResult::Continue;
     }
@@ -1032,6 +2285,7 @@ impl srmarkup::IASTProcessor for HTMLProcessor {
     fn visit_begin_tag(
         &mut self,
         tag_node: &srmarkup::ASTNodeTag,
+        depth: usize,
     ) -> srmarkup::ASTProcessorVisitResult {
         let tag_text = tag_node.text.to_lowercase();
 
@@ -1045,95 +2299,163 @@ impl srmarkup::IASTProcessor for HTMLProcessor {
                 return srmarkup::ASTProcessorVisitResult::SkipChildren;
             }
             "file" => {
-                let file_path = tag_node.find_attribute("Source");
-
-                if file_path.is_some() {
-                    let file_path_string = file_path.unwrap().to_string();
-                    let file = File::open(&file_path_string);
-
-                    match file {
-                        Ok(mut file) => {
-                            let mut source = String::new();
-                            let source_size = file.read_to_string(&mut source);
-
-                            match source_size {
-                                Ok(_) => {
-                                    self.visit_text(&ASTNodeText { text: source });
-                                }
-                                Err(msg) => {
-                                    eprintln!(
-                                        "[ERROR] Failed to read file ('{}'), {}.",
-                                        file_path_string, msg
-                                    );
-                                }
-                            }
-                        }
-                        Err(msg) => {
-                            eprintln!(
-                                "[ERROR] Failed to load file ('{}'), {}.",
-                                file_path_string, msg
-                            );
-                        }
-                    }
+                self.include_file(tag_node, depth);
+
+                return srmarkup::ASTProcessorVisitResult::SkipChildren;
+            }
+            "code" => {
+                let pre_element = self.doc.create_element("pre");
+                let code_element = self.doc.create_element("code");
+                let css_classes = self.extract_classes(tag_node);
+                let language = tag_node.find_attribute_ci("Lang");
+
+                // NOTE(SR): `language-*` is the class highlighters (highlight.js, Prism, ...) look for.
+                let css_classes = match language {
+                    Some(language) if css_classes.is_empty() => format!("language-{}", language),
+                    Some(language) => format!("language-{} {}", language, css_classes),
+                    None => css_classes,
+                };
+
+                if !css_classes.is_empty() {
+                    self.doc.set_attribute(code_element, "class", css_classes);
                 }
 
+                let current_element = *self.element_stack.last().unwrap();
+
+                let mut code_text = String::new();
+                HTMLProcessor::collect_verbatim_text(&tag_node.children, &mut code_text);
+
+                // NOTE(SR): The line break after the '{' and the indentation before the '}' are not part of the sample.
+                let code_text = code_text
+                    .strip_prefix('\n')
+                    .unwrap_or(&code_text)
+                    .trim_end();
+
+                self.doc.push_content(
+                    code_element,
+                    html::ElementContent::Text(html::escape(code_text)),
+                );
+                self.doc
+                    .push_content(pre_element, html::ElementContent::Element(code_element));
+                self.doc
+                    .push_content(current_element, html::ElementContent::Element(pre_element));
+
                 return srmarkup::ASTProcessorVisitResult::SkipChildren;
             }
             raw_tag => {
-                let real_tag = HTMLProcessor::remap_tag(raw_tag);
-                let css_classes = HTMLProcessor::extract_classes(&tag_node);
-                let css_id = tag_node.find_attribute("ID");
-                let src = tag_node.find_attribute("Src");
+                let real_tag_string = self.remap_tag(raw_tag).to_string();
+                let real_tag = real_tag_string.as_str();
+                let css_classes = self.extract_classes(tag_node);
+                let css_id = tag_node.find_attribute_ci("ID");
+                let src = tag_node.find_attribute_ci("Src");
+
+                if self.strict && !self.doc.is_known_tag(real_tag) {
+                    self.errors
+                        .push(GenError::UnknownTag(tag_node.text.clone()));
+                }
+
+                if self.strict {
+                    self.check_attribute_types(tag_node);
+                }
 
                 let element = self.doc.create_element(real_tag);
 
                 let is_video = real_tag == "video";
 
                 if !css_classes.is_empty() {
-                    self.doc
-                        .set_attribute(element, &"class".to_string(), css_classes);
+                    self.doc.set_attribute(element, "class", css_classes);
                 }
 
-                if css_id.is_some() {
-                    self.doc
-                        .set_attribute(element, &"id".to_string(), css_id.unwrap().to_string());
+                if let Some(css_id) = css_id {
+                    let id_string = css_id.to_string();
+                    self.defined_ids.insert(id_string.clone());
+                    self.doc.set_attribute(element, "id", id_string);
+                }
+
+                // NOTE(SR): Headings get an id from their text so they can be linked to (`#getting-started`).
+                if let Some(level) = HTMLProcessor::heading_level(real_tag) {
+                    let heading_text = HTMLProcessor::heading_text(tag_node);
+                    let heading_id = match css_id {
+                        Some(id) => Some(id.to_string()),
+                        None => self.unique_heading_id(&heading_text),
+                    };
+
+                    if let Some(id_string) = heading_id {
+                        if css_id.is_none() {
+                            self.defined_ids.insert(id_string.clone());
+                            self.doc.set_attribute(element, "id", id_string.clone());
+                        }
+
+                        self.headings.push(HeadingEntry {
+                            level,
+                            id: id_string,
+                            text: heading_text,
+                        });
+                    }
                 }
 
                 if is_video {
                     self.doc
-                        .set_attribute(element, &"controls".to_string(), "".to_string());
+                        .set_attribute(element, "controls", html::AttrValue::Boolean);
+                }
+
+                // NOTE(SR): Namespaced attributes (`xlink:href`) have no srmark meaning, they are written out as is.
+                for (key, value) in tag_node.attributes.iter() {
+                    if key.contains(':') {
+                        self.doc.set_attribute(element, key, value.to_string());
+                    }
                 }
 
-                if src.is_some() {
-                    let source_string = src.unwrap().to_string();
+                if let Some(src) = src {
+                    let mut source_string = src.to_string();
+
+                    if self.encode_urls {
+                        source_string = html::encode_url_path(&source_string);
+                    }
 
                     if is_video {
                         let source_element = self.doc.create_element("source");
-                        self.doc.set_attribute(
-                            source_element,
-                            &"type".to_string(),
-                            "video/mp4".to_string(),
-                        );
                         self.doc
-                            .set_attribute(source_element, &"src".to_string(), source_string);
+                            .set_attribute(source_element, "type", "video/mp4".to_string());
+                        self.doc.set_attribute(source_element, "src", source_string);
 
                         self.doc
                             .push_content(element, html::ElementContent::Element(source_element));
                     } else if real_tag == "img" {
-                        self.doc
-                            .set_attribute(element, &"src".to_string(), source_string);
+                        self.doc.set_attribute(element, "src", source_string);
                         self.doc.set_attribute(
                             element,
-                            &"alt".to_string(),
+                            "alt",
                             HTMLProcessor::find_attribute_str(tag_node, "Alt"),
                         );
                     } else if real_tag == "a" {
-                        self.doc
-                            .set_attribute(element, &"href".to_string(), source_string);
+                        if let Some(id) = source_string.strip_prefix('#') {
+                            self.referenced_ids.push(id.to_string());
+                        }
+                        self.doc.set_attribute(element, "href", source_string);
                     }
                 }
 
-                let current_element = *self.element_stack.last().unwrap();
+                let mut current_element = *self.element_stack.last().unwrap();
+                let parent_tag = self.doc.tag_name(current_element).to_string();
+
+                // NOTE(SR): Rows written directly in a table get the implicit `<tbody>` the browser would add.
+                if real_tag == "tr" && parent_tag == "table" {
+                    current_element = self.implicit_table_body(current_element);
+                }
+
+                if (real_tag == "td" || real_tag == "th") && parent_tag != "tr" {
+                    let error = GenError::CellPlacement {
+                        tag: tag_node.text.clone(),
+                        parent: parent_tag,
+                    };
+
+                    if self.strict {
+                        self.errors.push(error);
+                    } else {
+                        self.warnings.push(error);
+                    }
+                }
 
                 self.doc
                     .push_content(current_element, html::ElementContent::Element(element));
@@ -1148,8 +2470,23 @@ impl srmarkup::IASTProcessor for HTMLProcessor {
     fn visit_text(
         &mut self,
         text_node: &srmarkup::ASTNodeText,
+        _depth: usize,
     ) -> srmarkup::ASTProcessorVisitResult {
         let current_element = *self.element_stack.last().unwrap();
+
+        // NOTE(SR): The space written between two block tags (`@tr{..} @tr{..}`) means nothing in html.
+        if text_node.text.trim().is_empty() {
+            let last_content = self
+                .doc
+                .get_const_element_by_id(current_element)
+                .contents
+                .last();
+
+            if last_content.is_some_and(|content| !self.doc.is_inline_content(content)) {
+                return srmarkup::ASTProcessorVisitResult::Continue;
+            }
+        }
+
         let sanitized_string = html::escape(text_node.text.as_str());
 
         self.doc.push_content(
@@ -1163,22 +2500,907 @@ impl srmarkup::IASTProcessor for HTMLProcessor {
     fn visit_literal(
         &mut self,
         literal_node: &srmarkup::ASTNodeLiteral,
+        _depth: usize,
     ) -> srmarkup::ASTProcessorVisitResult {
         let current_element = *self.element_stack.last().unwrap();
+        let literal_text = html::ElementContent::Text(html::escape(&literal_node.to_string()));
 
-        self.doc.push_content(
-            current_element,
-            html::ElementContent::Text(literal_node.to_string()),
-        );
+        let literal_class = match literal_node {
+            srmarkup::ASTNodeLiteral::Float { .. } => Some("literal-number"),
+            srmarkup::ASTNodeLiteral::Measure { .. } => Some("literal-number"),
+            srmarkup::ASTNodeLiteral::Bool(_) => Some("literal-bool"),
+            srmarkup::ASTNodeLiteral::Str(_) | srmarkup::ASTNodeLiteral::List(_) => None,
+        };
+
+        if let Some(literal_class) = literal_class.filter(|_| self.typed_literal_spans) {
+            let span = self.doc.create_element("span");
+            self.doc
+                .set_attribute(span, "class", literal_class.to_string());
+            self.doc.push_content(span, literal_text);
+            self.doc
+                .push_content(current_element, html::ElementContent::Element(span));
+        } else {
+            self.doc.push_content(current_element, literal_text);
+        }
 
         return srmarkup::ASTProcessorVisitResult::Continue;
     }
 
-    fn visit_end_tag(&mut self, _: &srmarkup::ASTNodeTag) {
-        self.pop_element();
+    fn visit_end_tag(
+        &mut self,
+        tag_node: &srmarkup::ASTNodeTag,
+        _depth: usize,
+    ) -> srmarkup::ASTProcessorVisitResult {
+        // NOTE(SR): `header`, `file` and `code` never pushed an element in `visit_begin_tag`.
+        match tag_node.text.to_lowercase().as_str() {
+            "header" | "file" | "code" => {}
+            _ => self.pop_element(),
+        }
+
+        return srmarkup::ASTProcessorVisitResult::Continue;
     }
 
-    fn visit_end_root(&mut self, _: &srmarkup::ASTNodeRoot) {
+    fn visit_end_root(
+        &mut self,
+        _: &srmarkup::ASTNodeRoot,
+        _depth: usize,
+    ) -> srmarkup::ASTProcessorVisitResult {
         self.pop_element();
+
+        if self.strict {
+            for id in self.referenced_ids.iter() {
+                if !self.defined_ids.contains(id) {
+                    self.errors.push(GenError::UndefinedRef(id.clone()));
+                }
+            }
+        }
+
+        return srmarkup::ASTProcessorVisitResult::Continue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(source: &str, strict: bool) -> HTMLProcessor {
+        let root = parse_document(source.to_string(), false, true)
+            .ok()
+            .unwrap();
+        let mut processor = HTMLProcessor::new();
+        processor.strict = strict;
+
+        srmarkup::visit_ast(&root, &mut processor);
+
+        return processor;
+    }
+
+    fn messages(errors: &[GenError]) -> Vec<String> {
+        return errors.iter().map(|error| error.to_string()).collect();
+    }
+
+    #[test]
+    fn table_cells_inside_rows_are_accepted() {
+        let processor = generate("@table{ @tr{ @td{A} @td{B} } @tr{ @td{C} @td{D} } }", true);
+
+        assert!(processor.warnings.is_empty());
+        assert!(processor.errors.is_empty());
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::minified()),
+            "<table><tbody><tr><td>A</td><td>B</td></tr><tr><td>C</td><td>D</td></tr></tbody></table>"
+        );
+    }
+
+    #[test]
+    fn table_cell_outside_of_a_row_is_reported() {
+        let source = "@table{ @tr{ @td{A} @td{B} } @td{C} }";
+        let expected = ["'@td' must be placed inside of a '@tr' but was found in 'table'."];
+
+        let processor = generate(source, false);
+        assert_eq!(messages(&processor.warnings), expected);
+        assert!(processor.errors.is_empty());
+
+        let processor = generate(source, true);
+        assert!(processor.warnings.is_empty());
+        assert_eq!(messages(&processor.errors), expected);
+    }
+
+    #[test]
+    fn attribute_of_the_wrong_type_is_a_warning_when_strict() {
+        let source = "@image(Src=\"a.png\" Width=128 Height=\"64\" Hidden=\"maybe\")";
+
+        assert!(generate(source, false).warnings.is_empty());
+        assert_eq!(
+            messages(&generate(source, true).warnings),
+            ["'@image' attribute 'Hidden' should be a bool value."]
+        );
+    }
+
+    #[test]
+    fn number_literals_keep_their_text_when_preserved() {
+        let minified = html::RenderOptions::minified();
+
+        for (preserve_number_text, expected) in [(false, "<p>16</p>"), (true, "<p>16.0</p>")] {
+            let root = parse_document("@text{16.0}".to_string(), preserve_number_text, true)
+                .ok()
+                .unwrap();
+            let mut processor = HTMLProcessor::new();
+            srmarkup::visit_ast(&root, &mut processor);
+
+            assert_eq!(render_body(&processor.doc, &minified), expected);
+        }
+    }
+
+    #[test]
+    fn typed_literals_are_wrapped_in_spans_when_enabled() {
+        let minified = html::RenderOptions::minified();
+        let root = parse_document("@text{9.99} @text{true}".to_string(), false, true)
+            .ok()
+            .unwrap();
+
+        for (typed_literal_spans, expected) in [
+            (false, "<p>9.99</p><p>true</p>"),
+            (
+                true,
+                "<p><span class=\"literal-number\">9.99</span></p><p><span class=\"literal-bool\">true</span></p>",
+            ),
+        ] {
+            let mut processor = HTMLProcessor::new();
+            processor.typed_literal_spans = typed_literal_spans;
+            srmarkup::visit_ast(&root, &mut processor);
+
+            assert_eq!(render_body(&processor.doc, &minified), expected);
+        }
+    }
+
+    #[test]
+    fn tag_table_is_sorted_without_duplicates() {
+        let doc = html::Document::default();
+
+        for pair in doc.tags.windows(2) {
+            assert!(
+                pair[0].start_tag < pair[1].start_tag,
+                "'{}' then '{}'",
+                pair[0].start_tag,
+                pair[1].start_tag
+            );
+        }
+
+        for tag in ["main", "dl", "param", "h1"] {
+            assert!(doc.is_known_tag(tag), "'{}' is missing", tag);
+        }
+    }
+
+    #[test]
+    fn tag_id_from_string_resolves_every_built_in() {
+        let mut doc = html::Document::default();
+        let tag_names: Vec<String> = doc.tags.iter().map(|tag| tag.start_tag.clone()).collect();
+
+        for (index, tag_name) in tag_names.iter().enumerate() {
+            assert_eq!(doc.tag_id_from_string(tag_name) as usize, index);
+            assert_eq!(
+                doc.tag_id_from_string(&tag_name.to_uppercase()) as usize,
+                index
+            );
+        }
+
+        assert_eq!(doc.tags.len(), tag_names.len());
+    }
+
+    fn render_source(source: &str, options: &html::RenderOptions) -> String {
+        return render_body(&generate(source, false).doc, options);
+    }
+
+    #[test]
+    fn compact_output_keeps_the_spaces_as_written() {
+        let source = "@text{Some @b{un}believable and @i{italic} text, @b{one} @i{two}.}";
+        let expected =
+            "<p>Some <b>un</b>believable and <i>italic</i> text, <b>one</b> <i>two</i>.</p>";
+
+        assert_eq!(
+            render_source(source, &html::RenderOptions::compact()),
+            expected
+        );
+        assert_eq!(
+            render_source(source, &html::RenderOptions::minified()),
+            expected
+        );
+    }
+
+    #[test]
+    fn trimmed_tag_spacing_joins_inline_tags_to_their_text() {
+        let root = parse_document("@text{Some @b{un} believable}".to_string(), false, false)
+            .ok()
+            .unwrap();
+        let mut processor = HTMLProcessor::new();
+        processor.keep_tag_spacing = false;
+
+        srmarkup::visit_ast(&root, &mut processor);
+
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::compact()),
+            "<p>Some<b>un</b>believable</p>"
+        );
+    }
+
+    #[test]
+    fn compact_output_does_not_split_numbers_from_text() {
+        assert_eq!(
+            render_source(
+                "@text{Chapter 2D Physics and @b{2}D}",
+                &html::RenderOptions::compact()
+            ),
+            "<p>Chapter 2D Physics and <b>2</b>D</p>"
+        );
+    }
+
+    #[test]
+    fn pretty_output_keeps_inline_content_on_one_line() {
+        let source = "@text{@b{un}believable and @i{@b{very} nested} @link(Src=\"#x\"){link}.}";
+
+        assert_eq!(
+            render_source(source, &html::RenderOptions::default()),
+            "<p>\n<b>un</b>believable and <i><b>very</b> nested</i> <a href=\"#x\">link</a>.\n</p>\n"
+        );
+        assert_eq!(
+            render_source("@text{2D Physics}", &html::RenderOptions::default()),
+            "<p>\n2D Physics\n</p>\n"
+        );
+    }
+
+    #[test]
+    fn preformatted_content_is_written_as_is() {
+        let source = "@pre{int a;\\n  return @b{a}+2;\\n}";
+        let expected = "<pre>int a;\n  return <b>a</b>+2;\n</pre>";
+
+        assert_eq!(
+            render_source(source, &html::RenderOptions::default()),
+            format!("{}\n", expected)
+        );
+        assert_eq!(
+            render_source(source, &html::RenderOptions::compact()),
+            expected
+        );
+    }
+
+    #[test]
+    fn pretty_and_compact_output_only_differ_around_blocks() {
+        let source = "@div{ @text{A @b{b}} @image(Src=\"x.png\") @image(Src=\"y.png\") }";
+        let mut pretty = html::RenderOptions::default();
+        pretty.indent = "  ".to_string();
+
+        assert_eq!(
+            render_source(source, &pretty),
+            concat!(
+                "<div>\n",
+                "  <p>\n",
+                "    A <b>b</b>\n",
+                "  </p>\n",
+                "  <img src=\"x.png\" alt=\"\"> <img src=\"y.png\" alt=\"\">\n",
+                "</div>\n",
+            )
+        );
+        assert_eq!(
+            render_source(source, &html::RenderOptions::compact()),
+            "<div><p>A <b>b</b></p><img src=\"x.png\" alt=\"\"> <img src=\"y.png\" alt=\"\"></div>"
+        );
+    }
+
+    #[test]
+    fn headings_get_unique_ids_from_their_text() {
+        let processor = generate(
+            "@h2{Getting Started} @h2{Getting @i{Started}} @h1{2D Physics} @h3(ID=\"own\"){Own}",
+            false,
+        );
+        let headings: Vec<(&str, &str)> = processor
+            .headings
+            .iter()
+            .map(|heading| (heading.id.as_str(), heading.text.as_str()))
+            .collect();
+
+        assert_eq!(
+            headings,
+            [
+                ("getting-started", "Getting Started"),
+                ("getting-started-1", "Getting Started"),
+                ("2d-physics", "2D Physics"),
+                ("own", "Own"),
+            ]
+        );
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::compact()),
+            concat!(
+                "<h2 id=\"getting-started\">Getting Started</h2>",
+                "<h2 id=\"getting-started-1\">Getting <i>Started</i></h2>",
+                "<h1 id=\"2d-physics\">2D Physics</h1>",
+                "<h3 id=\"own\">Own</h3>",
+            )
+        );
+    }
+
+    #[test]
+    fn table_of_contents_nests_by_heading_level() {
+        let mut processor = generate(
+            "@h1{Intro} @h2{Setup} @h3{Install} @h3{Build} @h2{Usage} @h1{Next}",
+            false,
+        );
+        let toc_list = processor.build_table_of_contents().unwrap();
+        let mut toc_content = std::io::BufWriter::new(Vec::new());
+
+        processor
+            .doc
+            .render(&mut toc_content, toc_list, &html::RenderOptions::compact());
+
+        assert_eq!(
+            String::from_utf8(toc_content.into_inner().unwrap()).unwrap(),
+            concat!(
+                "<ul>",
+                "<li><a href=\"#intro\">Intro</a><ul>",
+                "<li><a href=\"#setup\">Setup</a><ul>",
+                "<li><a href=\"#install\">Install</a></li>",
+                "<li><a href=\"#build\">Build</a></li>",
+                "</ul></li>",
+                "<li><a href=\"#usage\">Usage</a></li>",
+                "</ul></li>",
+                "<li><a href=\"#next\">Next</a></li>",
+                "</ul>",
+            )
+        );
+    }
+
+    #[test]
+    fn document_without_headings_has_no_table_of_contents() {
+        assert!(generate("@text{No headings}", false)
+            .build_table_of_contents()
+            .is_none());
+    }
+
+    #[test]
+    fn code_sample_keeps_its_whitespace_and_language_class() {
+        let source = "@code(Lang=\"rust\"){\nfn main() \\{\n    let a = 1 < 2;\n\\}\n}";
+        let expected = concat!(
+            "<pre><code class=\"language-rust\">",
+            "fn main() {\n    let a = 1 &lt; 2;\n}",
+            "</code></pre>",
+        );
+
+        assert_eq!(
+            render_source(source, &html::RenderOptions::default()),
+            format!("{}\n", expected)
+        );
+        assert_eq!(
+            render_source(source, &html::RenderOptions::minified()),
+            expected
+        );
+    }
+
+    #[test]
+    fn minified_output_has_no_newlines_between_block_tags() {
+        let source = concat!(
+            "@h1{Title}\n",
+            "@text{Some    spaced\n    out @b{text}.}\n",
+            "@ulist{\n  @listitem{One}\n  @listitem{Two}\n}\n",
+            "@pre{a\\n  b}\n",
+        );
+
+        assert_eq!(
+            render_source(source, &html::RenderOptions::minified()),
+            concat!(
+                "<h1 id=\"title\">Title</h1>",
+                "<p>Some spaced out <b>text</b>.</p>",
+                "<ul><li>One</li><li>Two</li></ul>",
+                "<pre>a\n  b</pre>",
+            )
+        );
+    }
+
+    /// Empty directory for a test's files, under the system temp directory.
+    fn scratch_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("srmark-{}-{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        return dir;
+    }
+
+    #[test]
+    fn output_file_is_written_with_its_parent_directories() {
+        let dir = scratch_dir("output");
+        let output_path = dir.join("posts/2024/post.html");
+        let output_path = output_path.to_str().unwrap();
+        let body = render_source("@h1{Title} @text{Body}", &html::RenderOptions::minified());
+
+        write_entire_file(output_path, &body).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(output_path).unwrap(),
+            "<h1 id=\"title\">Title</h1><p>Body</p>"
+        );
+
+        // NOTE(SR): A parent that is a file rather than a directory is reported, not a panic.
+        let blocked_path = dir.join("posts/2024/post.html/nested.html");
+        assert!(write_entire_file(blocked_path.to_str().unwrap(), &body).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Generates the document at `path` the way `main` does, includes are relative to its directory.
+    fn generate_file(path: &std::path::Path) -> HTMLProcessor {
+        return generate_file_with_limits(path, IncludeLimits::default());
+    }
+
+    fn generate_file_with_limits(
+        path: &std::path::Path,
+        include_limits: IncludeLimits,
+    ) -> HTMLProcessor {
+        let source = std::fs::read_to_string(path).unwrap();
+        let root = parse_document(source, false, true).ok().unwrap();
+        let context = srmarkup::ProcessorContext {
+            base_path: path.parent().unwrap().to_path_buf(),
+            ..Default::default()
+        };
+        let mut processor = HTMLProcessor::new();
+        processor.include_limits = include_limits;
+        processor.include_stack.push(path.canonicalize().unwrap());
+
+        srmarkup::visit_ast_with_context(&root, &mut processor, &context);
+
+        return processor;
+    }
+
+    #[test]
+    fn includes_are_relative_to_the_including_file() {
+        let dir = scratch_dir("relative-include");
+        std::fs::create_dir_all(dir.join("parts")).unwrap();
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@text{@file(Source=\"parts/intro.srmark\" Parse=true)}",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("parts/intro.srmark"),
+            "@b{Intro} @file(Source=\"note.txt\")",
+        )
+        .unwrap();
+        std::fs::write(dir.join("parts/note.txt"), "from the note").unwrap();
+
+        // NOTE(SR): Tests run from the crate root, a lookup relative to the working directory would fail.
+        let processor = generate_file(&dir.join("post.srmark"));
+
+        assert!(
+            processor.errors.is_empty(),
+            "{:?}",
+            messages(&processor.errors)
+        );
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::compact()),
+            "<p><b>Intro</b> from the note</p>"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn self_include_is_an_error() {
+        let dir = scratch_dir("self-include");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@text{Before @file(Source=\"post.srmark\" Parse=true)}",
+        )
+        .unwrap();
+
+        let processor = generate_file(&dir.join("post.srmark"));
+
+        assert_eq!(
+            messages(&processor.errors),
+            ["File ('post.srmark') includes itself."]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_depth_is_capped() {
+        let dir = scratch_dir("include-depth");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@file(Source=\"a.srmark\" Parse=true)",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("a.srmark"),
+            "A @file(Source=\"b.srmark\" Parse=true)",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("b.srmark"),
+            "B @file(Source=\"c.srmark\" Parse=true)",
+        )
+        .unwrap();
+        std::fs::write(dir.join("c.srmark"), "C").unwrap();
+
+        let limits = IncludeLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let processor = generate_file_with_limits(&dir.join("post.srmark"), limits);
+
+        assert_eq!(
+            messages(&processor.errors),
+            ["Including file ('c.srmark') exceeds the maximum include depth of 2."]
+        );
+
+        let limits = IncludeLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+        assert!(generate_file_with_limits(&dir.join("post.srmark"), limits)
+            .errors
+            .is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn total_includes_are_capped() {
+        let dir = scratch_dir("include-count");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@text{@file(Source=\"x.txt\") @file(Source=\"x.txt\") @file(Source=\"x.txt\") @file(Source=\"x.txt\")}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("x.txt"), "x").unwrap();
+
+        let limits = IncludeLimits {
+            max_total: 3,
+            ..Default::default()
+        };
+        let processor = generate_file_with_limits(&dir.join("post.srmark"), limits);
+
+        assert_eq!(
+            messages(&processor.errors),
+            ["Including file ('x.txt') exceeds the maximum of 3 included files."]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_include_is_reported() {
+        let dir = scratch_dir("missing-include");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@text{Before @file(Source=\"missing.srmark\") after}",
+        )
+        .unwrap();
+
+        let processor = generate_file(&dir.join("post.srmark"));
+        let errors = messages(&processor.errors);
+
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("Failed to load file ('missing.srmark'), "),
+            "{:?}",
+            errors
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parsed_include_is_composed_into_the_document() {
+        let dir = scratch_dir("parsed-include");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@h1{Post} @file(Source=\"card.srmark\" Parse=true) @text{@file(Source=\"card.srmark\")}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("card.srmark"), "@div{@text{A @b{bold} card}}").unwrap();
+
+        let processor = generate_file(&dir.join("post.srmark"));
+
+        assert!(
+            processor.errors.is_empty(),
+            "{:?}",
+            messages(&processor.errors)
+        );
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::compact()),
+            concat!(
+                "<h1 id=\"post\">Post</h1>",
+                "<div><p>A <b>bold</b> card</p></div>",
+                "<p>@div{@text{A @b{bold} card}}</p>",
+            )
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_errors_in_an_include_name_the_file() {
+        let dir = scratch_dir("include-parse-error");
+        std::fs::write(
+            dir.join("post.srmark"),
+            "@file(Source=\"broken.srmark\" Parse=true)",
+        )
+        .unwrap();
+        std::fs::write(dir.join("broken.srmark"), "@text{a = b}").unwrap();
+
+        let processor = generate_file(&dir.join("post.srmark"));
+        let errors = messages(&processor.errors);
+
+        assert!(!errors.is_empty());
+        assert!(
+            errors
+                .iter()
+                .all(|error| error.starts_with("broken.srmark: Line(")),
+            "{:?}",
+            errors
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn render_element(
+        doc: &html::Document,
+        element: html::ElementID,
+        options: &html::RenderOptions,
+    ) -> String {
+        let mut content = std::io::BufWriter::new(Vec::new());
+        doc.render(&mut content, element, options);
+
+        return String::from_utf8(content.into_inner().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn void_elements_in_both_styles() {
+        let mut doc = html::Document::default();
+        let paragraph = doc.create_element("p");
+        let line_break = doc.create_element("br");
+        let image = doc.create_element("img");
+
+        doc.set_attribute(image, "src", "a.png");
+        doc.set_attribute(image, "alt", "A");
+        // NOTE(SR): Content pushed into a void element is never written and does not add a closing tag.
+        doc.push_content(line_break, html::ElementContent::Text("lost".to_string()));
+        doc.push_content(paragraph, html::ElementContent::Text("One".to_string()));
+        doc.push_content(paragraph, html::ElementContent::Element(line_break));
+        doc.push_content(paragraph, html::ElementContent::Element(image));
+
+        let mut options = html::RenderOptions::compact();
+        assert_eq!(
+            render_element(&doc, paragraph, &options),
+            "<p>One<br><img src=\"a.png\" alt=\"A\"></p>"
+        );
+
+        options.self_close_void = true;
+        assert_eq!(
+            render_element(&doc, paragraph, &options),
+            "<p>One<br /><img src=\"a.png\" alt=\"A\" /></p>"
+        );
+    }
+
+    #[test]
+    fn boolean_and_empty_text_attributes_render_differently() {
+        let mut doc = html::Document::default();
+        let video = doc.create_element("video");
+        let image = doc.create_element("img");
+
+        doc.set_attribute(video, "controls", html::AttrValue::Boolean);
+        doc.set_attribute(video, "src", "clip.mp4");
+        doc.set_attribute(image, "alt", "");
+        doc.set_attribute(image, "hidden", html::AttrValue::Boolean);
+
+        let options = html::RenderOptions::compact();
+        assert_eq!(
+            render_element(&doc, video, &options),
+            "<video controls src=\"clip.mp4\"></video>"
+        );
+        assert_eq!(
+            render_element(&doc, image, &options),
+            "<img alt=\"\" hidden>"
+        );
+
+        // NOTE(SR): Setting a value again changes its kind in place.
+        doc.set_attribute(video, "controls", "");
+        assert_eq!(
+            render_element(&doc, video, &options),
+            "<video controls=\"\" src=\"clip.mp4\"></video>"
+        );
+    }
+
+    #[test]
+    fn attribute_names_are_case_insensitive() {
+        let mut doc = html::Document::default();
+        let link = doc.create_element("a");
+
+        doc.set_attribute(link, "HREF", "#top");
+        doc.set_attribute(link, "Title", "Top");
+        doc.remove_attribute(link, "TITLE");
+
+        assert_eq!(
+            render_element(&doc, link, &html::RenderOptions::compact()),
+            "<a href=\"#top\"></a>"
+        );
+    }
+
+    #[test]
+    fn attribute_order_is_stable_across_renders() {
+        let mut doc = html::Document::default();
+        let link = doc.create_element("a");
+
+        doc.set_attribute(link, "href", "#top");
+        doc.set_attribute(link, "title", "Top");
+        doc.set_attribute(link, "rel", "bookmark");
+        doc.set_attribute(link, "class", "nav");
+        doc.remove_attribute(link, "title");
+        doc.set_attribute(link, "href", "#start");
+        doc.set_attribute(link, "title", "Start");
+
+        let options = html::RenderOptions::compact();
+        for _ in 0..8 {
+            assert_eq!(
+                render_element(&doc, link, &options),
+                "<a href=\"#start\" rel=\"bookmark\" class=\"nav\" title=\"Start\"></a>"
+            );
+        }
+
+        let source = "@image(Src=\"a.png\" Alt=\"A\" ID=\"cover\" Class=\"wide\")";
+        // NOTE(SR): Each render parses again, the source's attribute map is not what decides the order.
+        for _ in 0..8 {
+            assert_eq!(
+                render_source(source, &options),
+                "<img class=\"wide\" id=\"cover\" src=\"a.png\" alt=\"A\">"
+            );
+        }
+    }
+
+    #[test]
+    fn url_paths_are_percent_encoded() {
+        assert_eq!(
+            html::encode_url_path("images/my photo.png"),
+            "images/my%20photo.png"
+        );
+        assert_eq!(
+            html::encode_url_path("café/über.png"),
+            "caf%C3%A9/%C3%BCber.png"
+        );
+        assert_eq!(
+            html::encode_url_path("https://example.com/a b/c.png?size=large big#top"),
+            "https://example.com/a%20b/c.png?size=large big#top"
+        );
+    }
+
+    #[test]
+    fn encoded_urls_are_left_unchanged() {
+        for url in [
+            "images/my%20photo.png",
+            "https://example.com/caf%C3%A9/",
+            "/plain/path-1_2.~png",
+        ] {
+            assert_eq!(html::encode_url_path(url), url);
+        }
+
+        // NOTE(SR): A '%' that does not start an escape is encoded itself.
+        assert_eq!(html::encode_url_path("100%.png"), "100%25.png");
+    }
+
+    #[test]
+    fn image_sources_are_encoded_when_enabled() {
+        let root = parse_document(
+            "@image(Src=\"my photo.png\" Alt=\"A\")".to_string(),
+            false,
+            true,
+        )
+        .ok()
+        .unwrap();
+
+        for (encode_urls, expected) in [
+            (false, "<img src=\"my photo.png\" alt=\"A\">"),
+            (true, "<img src=\"my%20photo.png\" alt=\"A\">"),
+        ] {
+            let mut processor = HTMLProcessor::new();
+            processor.encode_urls = encode_urls;
+            srmarkup::visit_ast(&root, &mut processor);
+
+            assert_eq!(
+                render_body(&processor.doc, &html::RenderOptions::compact()),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn crlf_line_endings_are_written_as_crlf_bytes() {
+        assert_eq!(
+            with_line_endings("a\nb\r\nc\n", "\r\n").as_bytes(),
+            b"a\r\nb\r\nc\r\n"
+        );
+        assert_eq!(with_line_endings("a\r\nb\n", "\n").as_bytes(), b"a\nb\n");
+
+        let rendered = render_source(
+            "@ulist{ @listitem{A} @listitem{B} }",
+            &html::RenderOptions::default(),
+        );
+        let crlf = with_line_endings(&rendered, "\r\n");
+
+        assert!(rendered.contains('\n'));
+        assert_eq!(crlf.matches("\r\n").count(), rendered.matches('\n').count());
+        assert!(!crlf.replace("\r\n", "").contains('\n'));
+    }
+
+    #[test]
+    fn transforms_run_in_order_before_rendering() {
+        let mut processor = generate("@text{Post}", false);
+
+        processor
+            .transforms
+            .push(Box::new(|doc: &mut html::Document| {
+                let footer = doc.create_element("footer");
+                doc.push_content(footer, html::ElementContent::Text("Footer".to_string()));
+                doc.push_content(doc.body, html::ElementContent::Element(footer));
+            }));
+        processor
+            .transforms
+            .push(Box::new(|doc: &mut html::Document| {
+                doc.push_content(doc.body, html::ElementContent::Text("!".to_string()));
+            }));
+        processor.apply_transforms();
+
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::minified()),
+            "<p>Post</p><footer>Footer</footer>!"
+        );
+    }
+
+    #[test]
+    fn classes_are_deduplicated_and_sorted_when_asked() {
+        let root = parse_document(
+            "@div(Class=[\"zeta\", \"alpha zeta\", \"mid\"] Size=\"Full\"){x}".to_string(),
+            false,
+            true,
+        )
+        .ok()
+        .unwrap();
+        let tag = match root.as_ref() {
+            srmarkup::ASTNode::Root(root) => root.find_first_tag("div").unwrap(),
+            _ => unreachable!(),
+        };
+        let mut processor = HTMLProcessor::new();
+
+        assert_eq!(processor.extract_classes(tag), "zeta alpha mid post-full");
+
+        processor.sort_classes = true;
+        assert_eq!(processor.extract_classes(tag), "alpha mid post-full zeta");
+    }
+
+    #[test]
+    fn attribute_values_are_escaped() {
+        assert_eq!(
+            render_source(
+                "@link(Src=\"search?q=\\\"a\\\"&lt=<b>\"){x} @div(Class=\"say-\\\"hi\\\" <b>&c\"){y}",
+                &html::RenderOptions::minified()
+            ),
+            "<a href=\"search?q=&quot;a&quot;&amp;lt=&lt;b&gt;\">x</a><div class=\"say-&quot;hi&quot; &lt;b&gt;&amp;c\">y</div>"
+        );
+    }
+
+    #[test]
+    fn custom_tag_mappings_are_used_when_rendering() {
+        let root = parse_document(
+            "@Callout{Note} @text{Body} @aside{x}".to_string(),
+            false,
+            true,
+        )
+        .ok()
+        .unwrap();
+        let mut processor = HTMLProcessor::new();
+        processor.map_tag("Callout", "ASIDE").map_tag("text", "div");
+        srmarkup::visit_ast(&root, &mut processor);
+
+        assert_eq!(
+            render_body(&processor.doc, &html::RenderOptions::minified()),
+            "<aside>Note</aside><div>Body</div><aside>x</aside>"
+        );
     }
 }
@@ -0,0 +1,89 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   brace_check.rs
+//
+
+use crate::lexer::Lexer;
+use crate::lexer::LexerMode;
+use crate::lexer::Token;
+
+/// Cheap pre-check for editors that only looks at the token stream, no AST is built.
+///
+/// On failure returns the `(line_number, column_number)` of the first unmatched `{` / `}`,
+/// or of the `(` for an attribute list that is never closed, counted the same way as `ParseError`.
+pub fn check_brace_balance(source: &str) -> Result<(), (usize, usize)> {
+    let mut lexer = Lexer::new(source.to_string());
+    let mut open_braces: Vec<(usize, usize)> = Vec::new();
+    let mut previous_was_tag = false;
+
+    loop {
+        let token = lexer.get_next_token();
+        let position = (lexer.token_line_no, lexer.token_column_no);
+
+        match token {
+            // NOTE(SR): Same as the parser, only a '(' directly after a tag opens an attribute list.
+            Token::OpenParen if previous_was_tag => {
+                lexer.push_mode(LexerMode::Code);
+                loop {
+                    match lexer.get_next_token() {
+                        Token::CloseParen => break,
+                        Token::EndOfFile() => return Err(position),
+                        _ => {}
+                    }
+                }
+                lexer.pop_mode();
+            }
+            Token::OpenBrace => open_braces.push(position),
+            Token::CloseBrace => {
+                let matching_open_brace = open_braces.pop();
+
+                if matching_open_brace.is_none() {
+                    return Err(position);
+                }
+            }
+            Token::EndOfFile() => break,
+            _ => {}
+        }
+
+        previous_was_tag = matches!(token, Token::Tag(_));
+    }
+
+    return match open_braces.first() {
+        Some(position) => Err(*position),
+        None => Ok(()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balanced_input_is_ok() {
+        assert_eq!(check_brace_balance(""), Ok(()));
+        assert_eq!(
+            check_brace_balance("@div(class=\"a}\"){ @p{ Text (with parens) } }\n@hr"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn unclosed_brace_reports_where_it_was_opened() {
+        assert_eq!(check_brace_balance("@div{\n  @p{ Text }\n"), Err((1, 5)));
+        assert_eq!(check_brace_balance("@a{ @b{x} }\n  @c{ @d{y}"), Err((2, 5)));
+    }
+
+    #[test]
+    fn unmatched_closing_brace_is_reported() {
+        assert_eq!(check_brace_balance("@p{ x } }"), Err((1, 9)));
+        assert_eq!(check_brace_balance("é @p{ x }\n\t}"), Err((2, 2)));
+    }
+
+    #[test]
+    fn unclosed_attribute_list_reports_the_paren() {
+        assert_eq!(
+            check_brace_balance("@p{x}\n@img(Src=\"a.png\""),
+            Err((2, 5))
+        );
+    }
+}
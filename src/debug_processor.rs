@@ -0,0 +1,155 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   debug_processor.rs
+//
+
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+
+use std::fmt::Write;
+
+/// Renders an indented, human readable dump of the tree into `output`.
+pub struct DebugProcessor {
+    pub output: String,
+    indent_width: u32, // Number of `indent_char`s per level.
+    indent_char: char,
+}
+
+impl DebugProcessor {
+    pub fn new(indent_width: u32, indent_char: char) -> Self {
+        DebugProcessor {
+            output: String::new(),
+            indent_width,
+            indent_char,
+        }
+    }
+
+    fn write_indent(&mut self, depth: usize) {
+        for _i in 0..(depth * self.indent_width as usize) {
+            self.output.push(self.indent_char);
+        }
+    }
+}
+
+/// `Float(2.0)` / `Str("x")` style text for a literal, the number as written when the parser kept it.
+fn literal_debug_string(literal_node: &ASTNodeLiteral) -> String {
+    return match literal_node {
+        ASTNodeLiteral::Str(value) => format!("Str({:?})", value),
+        ASTNodeLiteral::Float { raw: Some(raw), .. } => format!("Float({})", raw),
+        ASTNodeLiteral::Float { value, raw: None } => format!("Float({:?})", value),
+        ASTNodeLiteral::Bool(value) => format!("Bool({})", value),
+        ASTNodeLiteral::Measure { value, unit } => format!("Measure({:?}{})", value, unit),
+        ASTNodeLiteral::List(items) => format!(
+            "List([{}])",
+            items
+                .iter()
+                .map(literal_debug_string)
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    };
+}
+
+// NOTE(SR): Writing into a String cannot fail so the `fmt::Result`s are ignored.
+
+impl IASTProcessor for DebugProcessor {
+    fn visit_begin_root(&mut self, _: &ASTNodeRoot, _: usize) -> ASTProcessorVisitResult {
+        let _ = writeln!(self.output, "(root-begin){{");
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, depth: usize) -> ASTProcessorVisitResult {
+        self.write_indent(depth);
+        let _ = writeln!(self.output, "Tag({}) {{", tag_node.text);
+
+        if !tag_node.attributes.is_empty() {
+            self.write_indent(depth + 2);
+            let _ = writeln!(self.output, "Attributes: ");
+
+            for attrib in &tag_node.attributes {
+                self.write_indent(depth + 3);
+                let _ = writeln!(
+                    self.output,
+                    "'{}' = {}",
+                    attrib.0,
+                    literal_debug_string(attrib.1)
+                );
+            }
+        }
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, depth: usize) -> ASTProcessorVisitResult {
+        self.write_indent(depth);
+        let _ = writeln!(self.output, "TEXT({})", text_node.text);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(
+        &mut self,
+        literal_node: &ASTNodeLiteral,
+        depth: usize,
+    ) -> ASTProcessorVisitResult {
+        self.write_indent(depth);
+        let _ = writeln!(
+            self.output,
+            "LITERAL({})",
+            literal_debug_string(literal_node)
+        );
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_tag(&mut self, _: &ASTNodeTag, depth: usize) -> ASTProcessorVisitResult {
+        self.write_indent(depth);
+        let _ = writeln!(self.output, "}}");
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_root(&mut self, _: &ASTNodeRoot, depth: usize) -> ASTProcessorVisitResult {
+        self.write_indent(depth);
+        let _ = writeln!(self.output, "}}(root-end)");
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(source: &str, preserve_number_text: bool) -> String {
+        let mut parser = crate::Parser::new(source.to_string());
+        parser.preserve_number_text = preserve_number_text;
+
+        let root = parser.parse().ok().unwrap();
+        let mut debug_processor = DebugProcessor::new(1, ' ');
+        crate::visit_ast(&root, &mut debug_processor);
+
+        return debug_processor.output;
+    }
+
+    #[test]
+    fn literals_are_written_with_their_kind() {
+        assert_eq!(
+            dump("@x(ratio=16.0 name=\"a\"){2}", false),
+            concat!(
+                "(root-begin){\n",
+                " Tag(x) {\n",
+                "   Attributes: \n",
+                "    'ratio' = Float(16.0)\n",
+                "    'name' = Str(\"a\")\n",
+                "  LITERAL(Float(2.0))\n",
+                " }\n",
+                "}(root-end)\n",
+            )
+        );
+    }
+
+    #[test]
+    fn preserved_numbers_are_written_as_typed() {
+        assert!(dump("@x{2.50}", true).contains("LITERAL(Float(2.50))"));
+    }
+}
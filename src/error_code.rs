@@ -0,0 +1,27 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   error_code.rs
+//
+
+/// Stable identifier of a lex / parse error so tools can react to it regardless of the message's wording.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ErrorCode {
+    UnterminatedString,
+    UnterminatedComment,
+    UnterminatedCodeSpan,
+    UnterminatedEscape,
+    InvalidEscape,
+    UnexpectedEndOfFile,
+    MalformedNumber,
+    InvalidTagName,
+    UnterminatedHtmlTag,
+    MismatchedClosingTag,
+    MissingClosingTag,
+    ExpectedAttributeName,
+    ExpectedAssignment,
+    ExpectedLiteral,
+    UnterminatedAttributeList,
+    UnterminatedList,
+    DuplicateAttribute,
+    UnexpectedCharacter,
+}
@@ -0,0 +1,468 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   html_stream.rs
+//
+
+//! Renders a document to html while it is being parsed, without ever building its `ASTNode` tree.
+//!
+//! The parser hands each node to a `TreeBuilder` in document order, `StreamingVisitor` forwards those to
+//! an `IASTProcessor` as they arrive and `HtmlStreamProcessor` writes each one out right away.
+//! Only the source text and the tags currently open are held in memory.
+
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+use crate::parser::ParseErrors;
+use crate::parser::Parser;
+use crate::parser::TreeBuilder;
+use crate::semantic::classify_tag;
+use crate::semantic::SemanticTag;
+
+use std::io::Read;
+use std::io::Write;
+
+/// How `render_html_streaming` parses the document.
+#[derive(Default, Clone)]
+pub struct HtmlStreamOptions {
+    /// Sets both `Parser::keep_space_before_tag` and `Parser::keep_space_after_tag`.
+    pub keep_space_around_tags: bool,
+    /// Forwarded to `Parser::verbatim_tags`.
+    pub verbatim_tags: Vec<String>,
+}
+
+pub enum StreamRenderError {
+    Io(std::io::Error),
+    /// The html written before the error was found is incomplete and should be thrown away.
+    Parse(ParseErrors),
+}
+
+impl From<std::io::Error> for StreamRenderError {
+    fn from(err: std::io::Error) -> Self {
+        return StreamRenderError::Io(err);
+    }
+}
+
+/// Parses the document read from `reader` and writes it to `writer` as html, tag by tag as it is parsed.
+///
+/// The source is read in full (the `Lexer` works on a `String`) but the tree is never built so memory
+/// stays bounded by the source size rather than the size of its tree. Tags are written with their
+/// html spelling (`@text` as `<p>`, `@link(Src=...)` as `<a href=...>`, see `HtmlStreamProcessor`),
+/// macros are not expanded.
+///
+/// ```
+/// let options = srmarkup::HtmlStreamOptions {
+///     keep_space_around_tags: true,
+///     ..Default::default()
+/// };
+/// let mut html = Vec::new();
+/// let result = srmarkup::render_html_streaming("@text{Hello @b{world}}".as_bytes(), &mut html, &options);
+///
+/// assert!(result.is_ok());
+/// assert_eq!(String::from_utf8(html).unwrap(), "<p>Hello <b>world</b></p>");
+/// ```
+pub fn render_html_streaming<R: Read, W: Write>(
+    mut reader: R,
+    writer: W,
+    options: &HtmlStreamOptions,
+) -> Result<(), StreamRenderError> {
+    let mut source = String::new();
+    reader.read_to_string(&mut source)?;
+
+    let mut parser = Parser::new(source);
+    parser.keep_space_before_tag = options.keep_space_around_tags;
+    parser.keep_space_after_tag = options.keep_space_around_tags;
+    parser.verbatim_tags = options.verbatim_tags.clone();
+
+    let mut processor = HtmlStreamProcessor::new(writer);
+    let mut visitor = StreamingVisitor::new(&mut processor);
+    let (_, errors) = parser.parse_tree(&mut visitor);
+
+    if !errors.is_empty() {
+        return Err(StreamRenderError::Parse(ParseErrors {
+            errors,
+            partial_root: None,
+        }));
+    }
+
+    return match processor.error {
+        Some(err) => Err(StreamRenderError::Io(err)),
+        None => Ok(()),
+    };
+}
+
+/// Calls an `IASTProcessor` for each node as the parser finishes it, with the same callbacks
+/// (and `SkipChildren` / `Halt` handling) `visit_ast` would make on the parsed tree.
+struct StreamingVisitor<'p> {
+    processor: &'p mut dyn IASTProcessor,
+    depth: usize,
+    skip_depth: Option<usize>, // Depth of the tag that asked for its children to be skipped.
+    is_halted: bool,
+}
+
+impl<'p> StreamingVisitor<'p> {
+    fn new(processor: &'p mut dyn IASTProcessor) -> Self {
+        StreamingVisitor {
+            processor,
+            depth: 0,
+            skip_depth: None,
+            is_halted: false,
+        }
+    }
+
+    fn is_visiting(&self) -> bool {
+        return !self.is_halted && self.skip_depth.is_none();
+    }
+
+    fn on_result(&mut self, result: ASTProcessorVisitResult) {
+        if result == ASTProcessorVisitResult::Halt {
+            self.is_halted = true;
+        }
+    }
+}
+
+impl<'p> TreeBuilder for StreamingVisitor<'p> {
+    type Node = ();
+
+    fn open_root(&mut self, root: &ASTNodeRoot) {
+        let result = self.processor.visit_begin_root(root, self.depth);
+
+        if result == ASTProcessorVisitResult::SkipChildren {
+            self.skip_depth = Some(self.depth);
+        }
+
+        self.on_result(result);
+        self.depth += 1;
+    }
+
+    fn open_tag(&mut self, tag: &ASTNodeTag) {
+        if self.is_visiting() {
+            let result = self.processor.visit_begin_tag(tag, self.depth);
+
+            if result == ASTProcessorVisitResult::SkipChildren {
+                self.skip_depth = Some(self.depth);
+            }
+
+            self.on_result(result);
+        }
+
+        self.depth += 1;
+    }
+
+    fn root(&mut self, root: ASTNodeRoot, _children: Vec<()>) {
+        self.depth -= 1;
+
+        if self.skip_depth == Some(self.depth) {
+            self.skip_depth = None;
+        }
+
+        if self.is_visiting() {
+            let result = self.processor.visit_end_root(&root, self.depth);
+            self.on_result(result);
+        }
+    }
+
+    fn tag(&mut self, tag: ASTNodeTag, _children: Vec<()>) {
+        self.depth -= 1;
+
+        if self.skip_depth == Some(self.depth) {
+            self.skip_depth = None;
+        }
+
+        if self.is_visiting() {
+            let result = self.processor.visit_end_tag(&tag, self.depth);
+            self.on_result(result);
+        }
+    }
+
+    fn text(&mut self, text: ASTNodeText) {
+        if self.is_visiting() {
+            let result = self.processor.visit_text(&text, self.depth);
+            self.on_result(result);
+        }
+    }
+
+    fn literal(&mut self, literal: ASTNodeLiteral) {
+        if self.is_visiting() {
+            let result = self.processor.visit_literal(&literal, self.depth);
+            self.on_result(result);
+        }
+    }
+}
+
+/// Writes each node to `writer` as html as soon as it is visited, nothing is buffered.
+///
+/// The common tags are written with their html name (`SemanticTag`), `Src` becomes `href` on links
+/// and `src` on images, every other tag and attribute is written lower cased as is.
+/// Text is written as it is in the tree, no whitespace is added between nodes.
+///
+/// The visit is halted on the first failed write, which is kept in `error`.
+pub struct HtmlStreamProcessor<W: Write> {
+    pub writer: W,
+    pub error: Option<std::io::Error>,
+}
+
+impl<W: Write> HtmlStreamProcessor<W> {
+    pub fn new(writer: W) -> Self {
+        HtmlStreamProcessor {
+            writer,
+            error: None,
+        }
+    }
+
+    fn write_str(&mut self, html: &str) -> ASTProcessorVisitResult {
+        if self.error.is_some() {
+            return ASTProcessorVisitResult::Halt;
+        }
+
+        if let Err(err) = self.writer.write_all(html.as_bytes()) {
+            self.error = Some(err);
+            return ASTProcessorVisitResult::Halt;
+        }
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn html_tag_name(tag_name: &str) -> String {
+        return match classify_tag(tag_name) {
+            Some(SemanticTag::Paragraph) => "p".to_string(),
+            Some(SemanticTag::Heading(level)) => format!("h{}", level),
+            Some(SemanticTag::UnorderedList) => "ul".to_string(),
+            Some(SemanticTag::OrderedList) => "ol".to_string(),
+            Some(SemanticTag::ListItem) => "li".to_string(),
+            Some(SemanticTag::Link) => "a".to_string(),
+            Some(SemanticTag::Image) => "img".to_string(),
+            None => tag_name.to_lowercase(),
+        };
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '\"' => result.push_str("&quot;"),
+            _ => result.push(c),
+        }
+    }
+
+    return result;
+}
+
+impl<W: Write> IASTProcessor for HtmlStreamProcessor<W> {
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, _depth: usize) -> ASTProcessorVisitResult {
+        let semantic_tag = classify_tag(&tag_node.text);
+        let mut html = format!(
+            "<{}",
+            HtmlStreamProcessor::<W>::html_tag_name(&tag_node.text)
+        );
+
+        for (key, value) in tag_node.attributes.iter() {
+            let key = match semantic_tag {
+                Some(SemanticTag::Link) if key.eq_ignore_ascii_case("src") => "href".to_string(),
+                _ => key.to_lowercase(),
+            };
+
+            html.push_str(&format!(" {}=\"{}\"", key, escape_html(&value.to_string())));
+        }
+
+        html.push('>');
+
+        let result = self.write_str(&html);
+
+        // NOTE(SR): Images are void elements, they have no content or closing tag.
+        if result == ASTProcessorVisitResult::Continue && semantic_tag == Some(SemanticTag::Image) {
+            return ASTProcessorVisitResult::SkipChildren;
+        }
+
+        return result;
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, _depth: usize) -> ASTProcessorVisitResult {
+        return self.write_str(&escape_html(&text_node.text));
+    }
+
+    fn visit_literal(
+        &mut self,
+        literal_node: &ASTNodeLiteral,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        return self.write_str(&escape_html(&literal_node.to_string()));
+    }
+
+    fn visit_end_tag(&mut self, tag_node: &ASTNodeTag, _depth: usize) -> ASTProcessorVisitResult {
+        if classify_tag(&tag_node.text) == Some(SemanticTag::Image) {
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        let html = format!(
+            "</{}>",
+            HtmlStreamProcessor::<W>::html_tag_name(&tag_node.text)
+        );
+
+        return self.write_str(&html);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_processor::visit_ast;
+
+    fn render_streaming(source: &str, options: &HtmlStreamOptions) -> String {
+        let mut html = Vec::new();
+        let result = render_html_streaming(source.as_bytes(), &mut html, options);
+
+        assert!(result.is_ok());
+
+        return String::from_utf8(html).unwrap();
+    }
+
+    fn render_buffered(source: &str, options: &HtmlStreamOptions) -> String {
+        let mut parser = Parser::new(source.to_string());
+        parser.keep_space_before_tag = options.keep_space_around_tags;
+        parser.keep_space_after_tag = options.keep_space_around_tags;
+        parser.verbatim_tags = options.verbatim_tags.clone();
+
+        let root = parser.parse().ok().unwrap();
+        let mut processor = HtmlStreamProcessor::new(Vec::new());
+        visit_ast(&root, &mut processor);
+
+        return String::from_utf8(processor.writer).unwrap();
+    }
+
+    fn generated_document(section_count: usize) -> String {
+        let mut source = String::new();
+
+        for i in 0..section_count {
+            source.push_str(&format!(
+                concat!(
+                    "@h2{{Section {i}}}\n",
+                    "@text{{Paragraph {i} with @b{{bold}} and @i{{nested @b{{text}}}}, see ",
+                    "@link(Src=\"#s{i}\"){{here}} & \"quotes\" < {i} @image(Src=\"img{i}.png\" Alt=\"Image {i}\")}}\n",
+                    "@ulist{{ @listitem{{{i}}} @listitem{{true}} @listitem{{Item}} }}\n",
+                    "@pre{{  keep   {i}\\n}}\n",
+                ),
+                i = i
+            ));
+        }
+
+        return source;
+    }
+
+    #[test]
+    fn streaming_matches_the_buffered_render() {
+        let source = generated_document(500);
+
+        for options in [
+            HtmlStreamOptions::default(),
+            HtmlStreamOptions {
+                keep_space_around_tags: true,
+                verbatim_tags: vec!["pre".to_string()],
+            },
+        ] {
+            let streamed = render_streaming(&source, &options);
+
+            assert_eq!(streamed, render_buffered(&source, &options));
+            assert!(streamed.contains("<a href=\"#s499\">here</a>"));
+        }
+    }
+
+    #[test]
+    fn tags_are_written_with_their_html_names() {
+        let options = HtmlStreamOptions {
+            keep_space_around_tags: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_streaming(
+                "@text{A @link(Src=\"a.html\"){link} to @image(Src=\"a.png\") & 5 < 6}",
+                &options
+            ),
+            "<p>A <a href=\"a.html\">link</a> to <img src=\"a.png\"> &amp; 5 &lt; 6</p>"
+        );
+    }
+
+    #[test]
+    fn namespaced_names_are_written_as_is() {
+        assert_eq!(
+            render_streaming(
+                "@svg:use(xlink:href=\"#icon\")",
+                &HtmlStreamOptions::default()
+            ),
+            "<svg:use xlink:href=\"#icon\"></svg:use>"
+        );
+    }
+
+    #[test]
+    fn parse_errors_are_returned() {
+        let mut html = Vec::new();
+        let result = render_html_streaming(
+            "@p(class \"x\"){ Hi }".as_bytes(),
+            &mut html,
+            &HtmlStreamOptions::default(),
+        );
+
+        match result {
+            Err(StreamRenderError::Parse(errors)) => assert_eq!(errors.errors.len(), 1),
+            _ => panic!("Expected a parse error"),
+        }
+    }
+
+    struct SkippingProcessor {
+        visited: Vec<String>,
+    }
+
+    impl IASTProcessor for SkippingProcessor {
+        fn visit_begin_tag(
+            &mut self,
+            tag_node: &ASTNodeTag,
+            depth: usize,
+        ) -> ASTProcessorVisitResult {
+            self.visited.push(format!("{} {}", tag_node.text, depth));
+
+            if tag_node.text == "skip" {
+                return ASTProcessorVisitResult::SkipChildren;
+            }
+
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        fn visit_end_tag(
+            &mut self,
+            tag_node: &ASTNodeTag,
+            depth: usize,
+        ) -> ASTProcessorVisitResult {
+            self.visited.push(format!("/{} {}", tag_node.text, depth));
+
+            if tag_node.text == "halt" {
+                return ASTProcessorVisitResult::Halt;
+            }
+
+            return ASTProcessorVisitResult::Continue;
+        }
+    }
+
+    #[test]
+    fn streaming_visit_follows_visit_ast() {
+        let source = "@a{ @skip{ @hidden{} } @b{ @c{} } @halt{} @after{} }";
+        let mut streamed = SkippingProcessor { visited: vec![] };
+        let mut buffered = SkippingProcessor { visited: vec![] };
+
+        Parser::new(source.to_string()).parse_tree(&mut StreamingVisitor::new(&mut streamed));
+        visit_ast(&crate::parser::parse(source).ok().unwrap(), &mut buffered);
+
+        assert_eq!(streamed.visited, buffered.visited);
+        assert_eq!(
+            streamed.visited,
+            ["a 1", "skip 2", "/skip 2", "b 2", "c 3", "/c 3", "/b 2", "halt 2", "/halt 2"]
+        );
+    }
+}
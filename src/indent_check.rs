@@ -0,0 +1,79 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   indent_check.rs
+//
+
+use crate::ast::ASTNode;
+
+/// Lint for whitespace preserving tags (`Parser::verbatim_tags`), where a line indented with both
+/// tabs and spaces renders differently depending on the tab width.
+///
+/// Returns the line numbers of every such line inside of one of `verbatim_tags`.
+pub fn check_mixed_indentation(root: &ASTNode, verbatim_tags: &[String]) -> Vec<usize> {
+    let mut mixed_lines = Vec::new();
+
+    check_node(root, verbatim_tags, false, &mut mixed_lines);
+
+    return mixed_lines;
+}
+
+fn check_node(
+    node: &ASTNode,
+    verbatim_tags: &[String],
+    is_verbatim: bool,
+    mixed_lines: &mut Vec<usize>,
+) {
+    match node {
+        ASTNode::Root(root_node) => {
+            for child in &root_node.children {
+                check_node(child, verbatim_tags, is_verbatim, mixed_lines);
+            }
+        }
+        ASTNode::Tag(tag_node) => {
+            // NOTE(SR): Tags nested in a verbatim body are lexed verbatim too.
+            let is_verbatim = is_verbatim || verbatim_tags.contains(&tag_node.text);
+
+            for child in &tag_node.children {
+                check_node(child, verbatim_tags, is_verbatim, mixed_lines);
+            }
+        }
+        ASTNode::Text(text_node) if is_verbatim => {
+            for (line_index, line) in text_node.text.split('\n').enumerate() {
+                let indentation = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+
+                if indentation.contains(' ') && indentation.contains('\t') {
+                    mixed_lines.push(text_node.span.start_line + line_index);
+                }
+            }
+        }
+        ASTNode::Text(_) | ASTNode::Literal(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn mixed_lines(source: &str) -> Vec<usize> {
+        let verbatim_tags = vec!["pre".to_string()];
+        let mut parser = Parser::new(source.to_string());
+        parser.verbatim_tags = verbatim_tags.clone();
+
+        let root = parser.parse().ok().unwrap();
+
+        return check_mixed_indentation(&root, &verbatim_tags);
+    }
+
+    #[test]
+    fn mixed_indentation_in_a_pre_block_is_flagged() {
+        let source = "@text{Intro}\n@pre{\n    spaces\n\ttab\n \tmixed\n\t  mixed again\n}";
+
+        assert_eq!(mixed_lines(source), [5, 6]);
+    }
+
+    #[test]
+    fn only_verbatim_bodies_are_checked() {
+        assert!(mixed_lines("@text{\n \tnot verbatim}\n@pre{\n    spaces\n\t\ttabs\n}").is_empty());
+    }
+}
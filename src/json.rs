@@ -0,0 +1,88 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   json.rs
+//
+
+use crate::ast::ASTNodeLiteral;
+use crate::ast::AttributeMap;
+
+use std::fmt::Write;
+
+// NOTE(SR): Just enough json writing for the processors, not worth a dependency.
+
+pub(crate) fn write_json_string(output: &mut String, value: &str) {
+    output.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+
+    output.push('"');
+}
+
+/// Numbers that json can not represent (NaN, infinity) are written as `null`.
+pub(crate) fn write_json_number(output: &mut String, value: f64) {
+    if value.is_finite() {
+        let _ = write!(output, "{}", value);
+    } else {
+        output.push_str("null");
+    }
+}
+
+/// Strings, numbers and bools map directly, a measure is `{"value": 50, "unit": "%"}` and a list an array.
+pub(crate) fn write_json_literal(output: &mut String, literal: &ASTNodeLiteral) {
+    match literal {
+        ASTNodeLiteral::Str(value) => write_json_string(output, value),
+        ASTNodeLiteral::Float { value, .. } => write_json_number(output, *value),
+        ASTNodeLiteral::Bool(value) => {
+            let _ = write!(output, "{}", value);
+        }
+        ASTNodeLiteral::Measure { value, unit } => {
+            output.push_str("{\"value\":");
+            write_json_number(output, *value);
+            output.push_str(",\"unit\":");
+            write_json_string(output, unit);
+            output.push('}');
+        }
+        ASTNodeLiteral::List(items) => {
+            output.push('[');
+
+            for (index, item) in items.iter().enumerate() {
+                if index != 0 {
+                    output.push(',');
+                }
+
+                write_json_literal(output, item);
+            }
+
+            output.push(']');
+        }
+    }
+}
+
+/// Keys are written in the order they were in the source.
+pub(crate) fn write_json_attributes(output: &mut String, attributes: &AttributeMap) {
+    output.push('{');
+
+    for (index, (key, value)) in attributes.iter().enumerate() {
+        if index != 0 {
+            output.push(',');
+        }
+
+        write_json_string(output, key);
+        output.push(':');
+        write_json_literal(output, value);
+    }
+
+    output.push('}');
+}
@@ -0,0 +1,103 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   json_lines_processor.rs
+//
+
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+use crate::json::write_json_attributes;
+use crate::json::write_json_literal;
+use crate::json::write_json_string;
+
+use std::io::Write;
+
+/// Streams one json object per line (JSONL) to `writer` for every callback as the tree is visited:
+///
+/// ```text
+/// {"event":"enter_root"}
+/// {"event":"enter_tag","name":"p","attributes":{"class":"x"},"line":1}
+/// {"event":"text","text":"Hello","line":1}
+/// {"event":"literal","value":42}
+/// {"event":"exit_tag","name":"p"}
+/// {"event":"exit_root"}
+/// ```
+///
+/// The visit is halted on the first failed write, which is kept in `error`.
+pub struct JsonLinesProcessor<W: Write> {
+    pub writer: W,
+    pub error: Option<std::io::Error>,
+}
+
+impl<W: Write> JsonLinesProcessor<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLinesProcessor {
+            writer,
+            error: None,
+        }
+    }
+
+    fn write_record(&mut self, record: String) -> ASTProcessorVisitResult {
+        if self.error.is_some() {
+            return ASTProcessorVisitResult::Halt;
+        }
+
+        if let Err(err) = writeln!(self.writer, "{}", record) {
+            self.error = Some(err);
+            return ASTProcessorVisitResult::Halt;
+        }
+
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+impl<W: Write> IASTProcessor for JsonLinesProcessor<W> {
+    fn visit_begin_root(&mut self, _: &ASTNodeRoot, _depth: usize) -> ASTProcessorVisitResult {
+        return self.write_record("{\"event\":\"enter_root\"}".to_string());
+    }
+
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, _depth: usize) -> ASTProcessorVisitResult {
+        let mut record = String::from("{\"event\":\"enter_tag\",\"name\":");
+        write_json_string(&mut record, &tag_node.text);
+        record.push_str(",\"attributes\":");
+        write_json_attributes(&mut record, &tag_node.attributes);
+        record.push_str(&format!(",\"line\":{}}}", tag_node.span.start_line));
+
+        return self.write_record(record);
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, _depth: usize) -> ASTProcessorVisitResult {
+        let mut record = String::from("{\"event\":\"text\",\"text\":");
+        write_json_string(&mut record, &text_node.text);
+        record.push_str(&format!(",\"line\":{}}}", text_node.span.start_line));
+
+        return self.write_record(record);
+    }
+
+    fn visit_literal(
+        &mut self,
+        literal_node: &ASTNodeLiteral,
+        _depth: usize,
+    ) -> ASTProcessorVisitResult {
+        let mut record = String::from("{\"event\":\"literal\",\"value\":");
+        write_json_literal(&mut record, literal_node);
+        record.push('}');
+
+        return self.write_record(record);
+    }
+
+    fn visit_end_tag(&mut self, tag_node: &ASTNodeTag, _depth: usize) -> ASTProcessorVisitResult {
+        let mut record = String::from("{\"event\":\"exit_tag\",\"name\":");
+        write_json_string(&mut record, &tag_node.text);
+        record.push('}');
+
+        return self.write_record(record);
+    }
+
+    fn visit_end_root(&mut self, _: &ASTNodeRoot, _depth: usize) -> ASTProcessorVisitResult {
+        return self.write_record("{\"event\":\"exit_root\"}".to_string());
+    }
+}
@@ -0,0 +1,199 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   json_processor.rs
+//
+
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast::Span;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+use crate::json::write_json_attributes;
+use crate::json::write_json_literal;
+use crate::json::write_json_string;
+
+/// Writes the whole tree as one json document into `output`, the structured counterpart of `DebugProcessor`.
+/// Every node is on its own line, indented by its depth, so dumps diff nicely:
+///
+/// ```
+/// let root = srmarkup::parse("@p(class=\"x\"){Hello}").ok().unwrap();
+/// let mut json = srmarkup::JsonProcessor::new();
+///
+/// srmarkup::visit_ast(&root, &mut json);
+///
+/// assert_eq!(
+///     json.output,
+///     concat!(
+///         "{\"type\":\"root\",\"children\":[\n",
+///         "  {\"type\":\"tag\",\"name\":\"p\",\"attributes\":{\"class\":\"x\"},\"children\":[\n",
+///         "    {\"type\":\"text\",\"text\":\"Hello\"}\n",
+///         "  ]}\n",
+///         "]}\n",
+///     )
+/// );
+/// ```
+///
+/// With `spans` set the root, tags and text also get a
+/// `"span":{"line":1,"column":1,"byte":0,"end_line":1}` so an editor can map a node back to the source.
+pub struct JsonProcessor {
+    pub output: String,
+    pub spans: bool,
+    has_children: Vec<bool>, // One entry per open root / tag, whether a child has been written yet.
+}
+
+impl JsonProcessor {
+    pub fn new() -> Self {
+        JsonProcessor {
+            output: String::new(),
+            spans: false,
+            has_children: Vec::new(),
+        }
+    }
+
+    fn begin_child(&mut self, depth: usize) {
+        if let Some(has_children) = self.has_children.last_mut() {
+            if *has_children {
+                self.output.push(',');
+            }
+
+            *has_children = true;
+            self.output.push('\n');
+        }
+
+        self.write_indent(depth);
+    }
+
+    fn end_children(&mut self, depth: usize) {
+        if self.has_children.pop() == Some(true) {
+            self.output.push('\n');
+            self.write_indent(depth);
+        }
+
+        self.output.push_str("]}");
+    }
+
+    fn write_span(&mut self, span: &Span) {
+        if self.spans {
+            self.output.push_str(&format!(
+                ",\"span\":{{\"line\":{},\"column\":{},\"byte\":{},\"end_line\":{}}}",
+                span.start_line, span.start_column, span.start_offset, span.end_line
+            ));
+        }
+    }
+
+    fn write_indent(&mut self, depth: usize) {
+        for _i in 0..(depth * 2) {
+            self.output.push(' ');
+        }
+    }
+}
+
+impl Default for JsonProcessor {
+    fn default() -> Self {
+        return JsonProcessor::new();
+    }
+}
+
+impl IASTProcessor for JsonProcessor {
+    fn visit_begin_root(
+        &mut self,
+        root_node: &ASTNodeRoot,
+        depth: usize,
+    ) -> ASTProcessorVisitResult {
+        self.begin_child(depth);
+        self.output.push_str("{\"type\":\"root\"");
+        self.write_span(&root_node.span);
+        self.output.push_str(",\"children\":[");
+        self.has_children.push(false);
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, depth: usize) -> ASTProcessorVisitResult {
+        self.begin_child(depth);
+        self.output.push_str("{\"type\":\"tag\",\"name\":");
+        write_json_string(&mut self.output, &tag_node.text);
+        self.write_span(&tag_node.span);
+        self.output.push_str(",\"attributes\":");
+        write_json_attributes(&mut self.output, &tag_node.attributes);
+        self.output.push_str(",\"children\":[");
+        self.has_children.push(false);
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, depth: usize) -> ASTProcessorVisitResult {
+        self.begin_child(depth);
+        self.output.push_str("{\"type\":\"text\",\"text\":");
+        write_json_string(&mut self.output, &text_node.text);
+        self.write_span(&text_node.span);
+        self.output.push('}');
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(
+        &mut self,
+        literal_node: &ASTNodeLiteral,
+        depth: usize,
+    ) -> ASTProcessorVisitResult {
+        self.begin_child(depth);
+        self.output.push_str("{\"type\":\"literal\",\"value\":");
+        write_json_literal(&mut self.output, literal_node);
+        self.output.push('}');
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_tag(&mut self, _: &ASTNodeTag, depth: usize) -> ASTProcessorVisitResult {
+        self.end_children(depth);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_root(&mut self, _: &ASTNodeRoot, depth: usize) -> ASTProcessorVisitResult {
+        self.end_children(depth);
+
+        if self.has_children.is_empty() {
+            self.output.push('\n');
+        }
+
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_json(source: &str, spans: bool) -> String {
+        let root = crate::parse(source).ok().unwrap();
+        let mut json = JsonProcessor::new();
+        json.spans = spans;
+
+        crate::visit_ast(&root, &mut json);
+
+        return json.output;
+    }
+
+    #[test]
+    fn spans_are_only_written_when_asked_for() {
+        let source = "@h1{Title}\n\n  @p(class=\"x\"){\n    Hello\n  }";
+
+        assert!(!to_json(source, false).contains("span"));
+        assert_eq!(
+            to_json(source, true),
+            concat!(
+                "{\"type\":\"root\",\"span\":{\"line\":1,\"column\":1,\"byte\":0,\"end_line\":5},\"children\":[\n",
+                "  {\"type\":\"tag\",\"name\":\"h1\",\"span\":{\"line\":1,\"column\":1,\"byte\":0,\"end_line\":1},\"attributes\":{},\"children\":[\n",
+                "    {\"type\":\"text\",\"text\":\"Title\",\"span\":{\"line\":1,\"column\":5,\"byte\":4,\"end_line\":1}}\n",
+                "  ]},\n",
+                "  {\"type\":\"tag\",\"name\":\"p\",\"span\":{\"line\":3,\"column\":3,\"byte\":14,\"end_line\":5},\"attributes\":{\"class\":\"x\"},\"children\":[\n",
+                "    {\"type\":\"text\",\"text\":\"Hello\",\"span\":{\"line\":4,\"column\":5,\"byte\":33,\"end_line\":4}}\n",
+                "  ]}\n",
+                "]}\n",
+            )
+        );
+    }
+}
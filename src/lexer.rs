@@ -3,6 +3,8 @@
 // File:   lexer.rs
 //
 
+use crate::error_code::ErrorCode;
+
 // Token
 
 #[derive(PartialEq, Debug, Clone)]
@@ -10,27 +12,55 @@ pub struct TokenTag {
     pub text: String,
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub struct TokenNumber {
+    pub value: f64,
+    pub text: String, // The number exactly as written in the source (without the unit).
+    pub unit: Option<String>, // `px` of `10px`, only lexed when `Lexer::numbers_with_units` is on.
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct TokenText {
     pub line_no_start: usize,
+    pub column_no_start: usize,
+    pub offset_start: usize,
     pub line_no_end_with_content: usize,
     pub line_no_end: usize,
     pub text: String,
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub struct TokenError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token {
     Tag(TokenTag),
     StringLiteral(String),
-    NumberLiteral(f64),
+    NumberLiteral(TokenNumber),
     BoolLiteral(bool),
     Text(TokenText),
-    Character(char),
-    Error(String),
+    HtmlOpenTag(TokenTag), // `<name`, the attributes and closing `>` / `/>` follow as separate tokens.
+    HtmlCloseTag(TokenTag), // `</name>`
+    CodeSpan(String), // `code` inside of text, only lexed when `Lexer::inline_code_spans` is on.
+    Equals,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    Character(char), // Any other single special character (',', '[' / ']' of lists, and '>' / '/' of html tags).
+
+    Error(TokenError),
     EndOfFile(),
 }
 
 impl Token {
+    pub fn error(code: ErrorCode, message: String) -> Token {
+        return Token::Error(TokenError { code, message });
+    }
+
     pub fn is_literal(&self) -> bool {
         match self {
             Token::StringLiteral(_value) => return true,
@@ -39,11 +69,53 @@ impl Token {
             _ => return false,
         }
     }
+
+    /// What sort of token this is without its contents, used for `Expected ...` style messages.
+    pub fn kind_name(&self) -> &'static str {
+        return match self {
+            Token::Tag(_) => "a tag",
+            Token::StringLiteral(_) => "a string",
+            Token::NumberLiteral(_) => "a number",
+            Token::BoolLiteral(_) => "a bool",
+            Token::Text(_) => "text",
+            Token::HtmlOpenTag(_) => "an html tag",
+            Token::HtmlCloseTag(_) => "a closing html tag",
+            Token::CodeSpan(_) => "a code span",
+            Token::Equals => "'='",
+            Token::OpenParen => "'('",
+            Token::CloseParen => "')'",
+            Token::OpenBrace => "'{'",
+            Token::CloseBrace => "'}'",
+            Token::Character(_) => "a character",
+            Token::Error(_) => "an error",
+            Token::EndOfFile() => "end of file",
+        };
+    }
+}
+
+/// A value along with the range of source bytes it was lexed from, `source[start_offset..end_offset]`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub start_offset: usize,
+    pub end_offset: usize,
 }
 
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Token::Tag(tag) => write!(f, "tag '@{}'", tag.text),
+            Token::StringLiteral(value) => write!(f, "string \"{}\"", value),
+            Token::NumberLiteral(number) => write!(f, "number '{}'", number.text),
+            Token::BoolLiteral(value) => write!(f, "bool '{}'", value),
+            Token::Text(text) => write!(f, "text '{}'", text.text),
+            Token::HtmlOpenTag(tag) => write!(f, "html tag '<{}'", tag.text),
+            Token::HtmlCloseTag(tag) => write!(f, "closing html tag '</{}>'", tag.text),
+            Token::CodeSpan(code) => write!(f, "code span `{}`", code),
+            Token::Character(c) => write!(f, "'{}'", c),
+            Token::Error(error) => write!(f, "error ({})", error.message),
+            _ => write!(f, "{}", self.kind_name()),
+        }
     }
 }
 
@@ -51,17 +123,41 @@ impl std::fmt::Display for Token {
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum LexerMode {
-    Text, // This mode is loose and will allow spaces in identifier type of characters groups.
+    Text,     // This mode is loose and will allow spaces in identifier type of characters groups.
     Code, // This mode is strict and will only allow identifiers typical of programming languages.
+    Verbatim, // Everything but '@', '{' and '}' is text, whitespace and newlines are kept exactly as written.
 }
 
 /// Turns a String into a stream of Tokens.
 pub struct Lexer {
     source: String,
-    cursor: usize,
-    pub line_no: usize,
+    cursor: usize, // Byte offset into `source`, always on a char boundary.
+    pub(crate) line_no: usize,
+    pub(crate) column_no: usize, // Counted in characters, starts at 1 on every line.
+    // Where the last token returned from `get_next_token` started.
+    pub token_line_no: usize,
+    pub token_column_no: usize,
+    pub token_after_whitespace: bool, // Whitespace (or a comment) was skipped right before it.
+    token_start_offset: usize,
+    text_end_offset: Option<usize>, // Text blocks end at their last content character, not the whitespace after.
     mode: LexerMode,
     mode_stack: Vec<LexerMode>,
+    peeked_token: Option<Spanned<Token>>, // Already lexed by `peek_token`, handed out by the next `get_next_token`.
+    /// Keeps one trailing space on a text block that runs into a tag (`word @b{x}`) instead of trimming it.
+    pub keep_space_before_tag: bool,
+    /// Recognizes `<tag attr="v">`, `</tag>` and `/>` in addition to `@tag` syntax.
+    pub html_tags: bool,
+    /// Ends a text block at a blank line rather than joining the paragraphs on either side.
+    pub split_text_at_blank_lines: bool,
+    /// Lets a number be directly followed by a unit (`50%`, `3fr`, `10px`).
+    pub numbers_with_units: bool,
+    /// Lexes `` `code` `` inside of text as a `Token::CodeSpan`, ``` `` ``` is a literal backtick.
+    pub inline_code_spans: bool,
+    /// Skips `// line` and `/* block */` comments between tokens and at the start of a line of text,
+    /// in the middle of a sentence they are left as text (`A // B`, `https://`).
+    pub comments: bool,
+    /// Keeps `${name}` inside of text as is for `expand_macros`, otherwise the '{' starts a block.
+    pub macro_placeholders: bool,
 }
 
 impl Lexer {
@@ -70,11 +166,35 @@ impl Lexer {
             source: src,
             cursor: 0,
             line_no: 1,
+            column_no: 1,
+            token_line_no: 1,
+            token_column_no: 1,
+            token_after_whitespace: false,
+            token_start_offset: 0,
+            text_end_offset: None,
             mode: LexerMode::Text,
             mode_stack: Default::default(),
+            peeked_token: None,
+            keep_space_before_tag: false,
+            html_tags: false,
+            split_text_at_blank_lines: false,
+            inline_code_spans: false,
+            numbers_with_units: false,
+            comments: false,
+            macro_placeholders: false,
         }
     }
 
+    /// Byte offset of the next character to be lexed.
+    pub fn offset(&self) -> usize {
+        return self.cursor;
+    }
+
+    /// Byte offset of where the last token returned from `get_next_token` started.
+    pub fn token_offset(&self) -> usize {
+        return self.token_start_offset;
+    }
+
     pub fn push_mode(&mut self, mode: LexerMode) {
         self.mode_stack.push(self.mode);
         self.mode = mode;
@@ -85,16 +205,80 @@ impl Lexer {
     }
 
     pub fn get_next_token(&mut self) -> Token {
+        return self.get_next_spanned_token().value;
+    }
+
+    /// Same as `get_next_token` but also reports the source bytes the token covers.
+    pub fn get_next_spanned_token(&mut self) -> Spanned<Token> {
+        if let Some(peeked_token) = self.peeked_token.take() {
+            return peeked_token;
+        }
+
+        return self.lex_spanned_token();
+    }
+
+    /// Looks at the next token without consuming it, the following `get_next_token` returns the same token.
+    ///
+    /// The token is lexed in the mode active at the time of the peek and `token_line_no` /
+    /// `token_column_no` already describe it, changing modes before consuming it has no effect.
+    pub fn peek_token(&mut self) -> &Token {
+        if self.peeked_token.is_none() {
+            self.peeked_token = Some(self.lex_spanned_token());
+        }
+
+        return &self.peeked_token.as_ref().unwrap().value;
+    }
+
+    fn lex_spanned_token(&mut self) -> Spanned<Token> {
+        self.text_end_offset = None;
+
+        let token = self.lex_token();
+        let end_offset = self.text_end_offset.take().unwrap_or(self.cursor);
+
+        return Spanned {
+            value: token,
+            start_offset: self.token_start_offset,
+            end_offset,
+        };
+    }
+
+    fn lex_token(&mut self) -> Token {
+        self.token_after_whitespace = false;
+
         while self.is_not_at_end() {
+            if self.mode == LexerMode::Verbatim && !matches!(self.current_char(), '@' | '{' | '}') {
+                self.token_line_no = self.line_no;
+                self.token_column_no = self.column_no;
+                self.token_start_offset = self.cursor;
+
+                return self.parse_text_block();
+            }
+
             if self.current_char().is_ascii_whitespace() {
                 self.skip_whitespace();
+                self.token_after_whitespace = true;
+                continue;
+            }
+
+            if self.is_comment_start() {
+                if let Err(err_token) = self.skip_comment() {
+                    return err_token;
+                }
+                self.token_after_whitespace = true;
                 continue;
             }
 
+            self.token_line_no = self.line_no;
+            self.token_column_no = self.column_no;
+            self.token_start_offset = self.cursor;
+
             let c = self.current_char();
 
             match c {
                 '@' => return self.parse_tag_name(),
+                '\"' if self.source[self.cursor..].starts_with("\"\"\"") => {
+                    return self.parse_raw_string()
+                }
                 '\"' => {
                     return match self.parse_quoted_string() {
                         Ok(quoted_string) => Token::StringLiteral(quoted_string),
@@ -102,24 +286,43 @@ impl Lexer {
                     }
                 }
                 '0'..='9' => return self.parse_numeric_literal(),
+                '<' if self.is_html_tag_start() => return self.parse_html_tag_name(),
+                '`' if self.is_code_span_start() => return self.parse_code_span(),
+                '>' if self.is_html_tag_end() => {
+                    self.advance_cursor(); // '>'
+                    return Token::Character('>');
+                }
+                '/' if self.is_html_tag_end() => {
+                    self.advance_cursor(); // '/' of '/>'
+                    return Token::Character('/');
+                }
+                // NOTE(SR): Signed numbers only exist in attribute lists, in prose `-5` is just text.
+                '-' | '+'
+                    if self.mode == LexerMode::Code
+                        && self.char_at(self.cursor + 1).is_ascii_digit() =>
+                {
+                    return self.parse_numeric_literal()
+                }
                 _ => {
-                    let src_len_left = self.source.len() - self.cursor;
-
                     if c.is_special_character(self.mode) {
                         self.advance_cursor(); // ','
-                        return Token::Character(c);
-                    } else if src_len_left >= 4
-                        && self.source[self.cursor..(self.cursor + 4)] == *"true"
-                    {
+
+                        return match c {
+                            '=' => Token::Equals,
+                            '(' => Token::OpenParen,
+                            ')' => Token::CloseParen,
+                            '{' => Token::OpenBrace,
+                            '}' => Token::CloseBrace,
+                            _ => Token::Character(c),
+                        };
+                    } else if self.is_keyword_at_cursor("true") {
                         self.advance_cursor(); // 't'
                         self.advance_cursor(); // 'r'
                         self.advance_cursor(); // 'u'
                         self.advance_cursor(); // 'e'
 
                         return Token::BoolLiteral(true);
-                    } else if src_len_left >= 5
-                        && self.source[self.cursor..(self.cursor + 4)] == *"false"
-                    {
+                    } else if self.is_keyword_at_cursor("false") {
                         self.advance_cursor(); // 'f'
                         self.advance_cursor(); // 'a'
                         self.advance_cursor(); // 'l'
@@ -133,11 +336,87 @@ impl Lexer {
             }
         }
 
+        self.token_line_no = self.line_no;
+        self.token_column_no = self.column_no;
+        self.token_start_offset = self.cursor;
+
         return Token::EndOfFile();
     }
 
+    fn is_comment_start(&self) -> bool {
+        if !self.comments || self.mode == LexerMode::Verbatim {
+            return false;
+        }
+
+        let next_two = self.source.get(self.cursor..(self.cursor + 2));
+
+        return next_two == Some("//") || next_two == Some("/*");
+    }
+
+    /// Skips a `// line` or `/* block */` comment, the cursor must be at the start of one.
+    fn skip_comment(&mut self) -> Result<(), Token> {
+        let is_block_comment = self.char_at(self.cursor + 1) == '*';
+
+        self.advance_cursor(); // '/'
+        self.advance_cursor(); // '/' or '*'
+
+        if is_block_comment {
+            while !self.source[self.cursor..].starts_with("*/") {
+                if self.is_at_end() {
+                    return Err(Token::error(
+                        ErrorCode::UnterminatedComment,
+                        "Unterminated block comment".to_string(),
+                    ));
+                }
+
+                self.advance_cursor();
+            }
+
+            self.advance_cursor(); // '*'
+            self.advance_cursor(); // '/'
+        } else {
+            while self.is_not_at_end() && !self.advance_cursor() {}
+        }
+
+        return Ok(());
+    }
+
+    /// The keyword must be a whole word, `trueish` and `falsey` are text.
+    fn is_keyword_at_cursor(&self, keyword: &str) -> bool {
+        let keyword_end = self.cursor + keyword.len();
+
+        if self.source.get(self.cursor..keyword_end) != Some(keyword) {
+            return false;
+        }
+
+        if keyword_end >= self.source.len() {
+            return true;
+        }
+
+        let next_char = self.char_at(keyword_end);
+
+        return next_char.is_ascii_whitespace()
+            || next_char.is_special_character(self.mode)
+            || next_char.is_text_block_ending_character(self.mode);
+    }
+
     fn parse_numeric_literal(&mut self) -> Token {
         let number_start = self.cursor;
+        let is_negative = self.current_char() == '-';
+
+        if is_negative || self.current_char() == '+' {
+            self.advance_cursor(); // '-' / '+'
+        }
+
+        match self.source.get(self.cursor..(self.cursor + 2)) {
+            Some("0x") | Some("0X") => {
+                return self.parse_radix_literal(number_start, 16, is_negative)
+            }
+            Some("0b") | Some("0B") => {
+                return self.parse_radix_literal(number_start, 2, is_negative)
+            }
+            _ => {}
+        }
 
         while self.current_char().is_ascii_digit() || self.current_char() == '.' {
             self.advance_cursor();
@@ -147,34 +426,237 @@ impl Lexer {
             }
         }
 
+        // NOTE(SR): Only an 'e' followed by the exponent's digits is part of the number, `5em` is `5` then text.
+        //           In an attribute list an 'e' that starts neither an exponent nor a word (`1e`, `1e+`) is malformed.
+        if self.current_char() == 'e' || self.current_char() == 'E' {
+            let after_e = self.char_at(self.cursor + 1);
+            let has_sign = after_e == '+' || after_e == '-';
+            let first_exponent_digit = if has_sign {
+                self.char_at(self.cursor + 2)
+            } else {
+                after_e
+            };
+
+            if first_exponent_digit.is_ascii_digit() {
+                self.advance_cursor(); // 'e'
+
+                if has_sign {
+                    self.advance_cursor(); // '+' / '-'
+                }
+
+                while self.current_char().is_ascii_digit() {
+                    self.advance_cursor();
+                }
+            } else if self.mode == LexerMode::Code && !after_e.is_alphabetic() {
+                self.advance_cursor(); // 'e'
+
+                if has_sign {
+                    self.advance_cursor(); // '+' / '-'
+                }
+
+                return Token::error(
+                    ErrorCode::MalformedNumber,
+                    format!(
+                        "Malformed number '{}', expected digits after the exponent",
+                        &self.source[number_start..self.cursor]
+                    ),
+                );
+            }
+        }
+
         let number_end = self.cursor;
-        let number = self.source[number_start..number_end].parse::<f64>();
+        let number_text = self.source[number_start..number_end].to_string();
+
+        if number_text.matches('.').count() > 1 {
+            return Token::error(
+                ErrorCode::MalformedNumber,
+                format!(
+                    "Malformed number '{}', only one decimal point is allowed",
+                    number_text
+                ),
+            );
+        }
+
+        let number = number_text.parse::<f64>();
+        let unit = if self.numbers_with_units {
+            self.parse_number_unit()
+        } else {
+            None
+        };
 
         match number {
-            Ok(value) => return Token::NumberLiteral(value),
-            Err(e) => return Token::Error(e.to_string()),
+            Ok(value) => {
+                return Token::NumberLiteral(TokenNumber {
+                    value,
+                    text: number_text,
+                    unit,
+                })
+            }
+            Err(e) => return Token::error(ErrorCode::MalformedNumber, e.to_string()),
         }
     }
 
+    /// Either a '%' or a run of letters directly after a number.
+    fn parse_number_unit(&mut self) -> Option<String> {
+        let unit_start = self.cursor;
+
+        if self.current_char() == '%' {
+            self.advance_cursor();
+        } else {
+            while self.current_char().is_ascii_alphabetic() {
+                self.advance_cursor();
+            }
+        }
+
+        if self.cursor == unit_start {
+            return None;
+        }
+
+        return Some(self.source[unit_start..self.cursor].to_string());
+    }
+
+    /// `0xFF` / `0b101` style integers, the cursor must be at the leading '0' (after any sign).
+    fn parse_radix_literal(&mut self, number_start: usize, radix: u32, is_negative: bool) -> Token {
+        self.advance_cursor(); // '0'
+        self.advance_cursor(); // 'x' / 'b'
+
+        let digits_start = self.cursor;
+
+        while self.current_char().is_ascii_alphanumeric() {
+            self.advance_cursor();
+        }
+
+        let number_text = &self.source[number_start..self.cursor];
+
+        return match u64::from_str_radix(&self.source[digits_start..self.cursor], radix) {
+            Ok(value) => Token::NumberLiteral(TokenNumber {
+                value: if is_negative {
+                    -(value as f64)
+                } else {
+                    value as f64
+                },
+                text: number_text.to_string(),
+                unit: None,
+            }),
+            Err(_) => Token::error(
+                ErrorCode::MalformedNumber,
+                format!(
+                    "Malformed number '{}', expected base {} digits after the prefix",
+                    number_text, radix
+                ),
+            ),
+        };
+    }
+
     fn parse_quoted_string(&mut self) -> Result<String, Token> {
+        let line_no_start = self.line_no;
+        let unterminated_error = || {
+            Token::error(
+                ErrorCode::UnterminatedString,
+                format!(
+                    "Unterminated string literal starting on line {}",
+                    line_no_start
+                ),
+            )
+        };
+
         self.advance_cursor(); // Skip over '"'
 
-        let name_start = self.cursor;
-        let mut name_length = 0;
+        let mut result = String::new();
 
         while self.current_char() != '\"' {
+            if self.is_at_end() {
+                return Err(unterminated_error());
+            }
+
+            let c = self.current_char();
             self.advance_cursor();
 
+            if c == '\\' {
+                if self.is_at_end() {
+                    return Err(unterminated_error());
+                }
+
+                result.push(self.parse_escaped_character()?);
+            } else {
+                result.push(c);
+            }
+        }
+
+        self.advance_cursor(); // Skip over '"'
+
+        return Ok(result);
+    }
+
+    /// `"""..."""`, everything between the delimiters is kept as is (no escapes, no whitespace folding).
+    fn parse_raw_string(&mut self) -> Token {
+        self.advance_cursor(); // '"'
+        self.advance_cursor(); // '"'
+        self.advance_cursor(); // '"'
+
+        let content_start = self.cursor;
+
+        while !self.source[self.cursor..].starts_with("\"\"\"") {
             if self.is_at_end() {
-                return Err(Token::Error("Unterminated Tag name string".to_string()));
+                return Token::error(
+                    ErrorCode::UnterminatedString,
+                    "Unterminated raw string".to_string(),
+                );
             }
 
-            name_length += 1;
+            self.advance_cursor();
         }
 
-        self.advance_cursor(); // Skip over '"'
+        let content = self.source[content_start..self.cursor].to_string();
+
+        self.advance_cursor(); // '"'
+        self.advance_cursor(); // '"'
+        self.advance_cursor(); // '"'
 
-        return Ok(self.source[name_start..(name_start + name_length)].to_string());
+        return Token::StringLiteral(content);
+    }
+
+    /// The character after a '\', either a `\u{1F600}` codepoint or a single character escape.
+    fn parse_escaped_character(&mut self) -> Result<char, Token> {
+        if self.current_char() != 'u' || self.char_at(self.cursor + 1) != '{' {
+            let escaped_character = self.current_char();
+            self.advance_cursor();
+
+            return Ok(escaped_character.unescaped());
+        }
+
+        self.advance_cursor(); // 'u'
+        self.advance_cursor(); // '{'
+
+        let digits_start = self.cursor;
+
+        while self.current_char().is_ascii_hexdigit() {
+            self.advance_cursor();
+        }
+
+        let digits = &self.source[digits_start..self.cursor];
+
+        if digits.is_empty() || self.current_char() != '}' {
+            return Err(Token::error(
+                ErrorCode::InvalidEscape,
+                "Malformed unicode escape, expected hex digits between '\\u{' and '}'".to_string(),
+            ));
+        }
+
+        let codepoint = u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32);
+
+        return match codepoint {
+            Some(c) => {
+                self.advance_cursor(); // '}'
+                Ok(c)
+            }
+            None => Err(Token::error(
+                ErrorCode::InvalidEscape,
+                format!("'\\u{{{}}}' is not a valid unicode codepoint", digits),
+            )),
+        };
     }
 
     fn parse_tag_name(&mut self) -> Token {
@@ -188,73 +670,266 @@ impl Lexer {
             };
         } else {
             let name_start = self.cursor;
-            let mut name_length = 0;
 
-            while self.current_char().is_ascii_alphanumeric()
-                || self.current_char().is_ascii_digit()
-                || self.current_char() == '_'
-            {
-                self.advance_cursor();
+            if self.current_char() == '-' {
+                return Token::error(
+                    ErrorCode::InvalidTagName,
+                    "Tag names can not start with a '-'".to_string(),
+                );
+            }
 
-                if self.is_at_end() {
-                    return Token::Error("Unterminated Tag name string".to_string());
-                }
-                name_length += 1;
+            // NOTE(SR): Slice by the cursor (a byte offset) rather than counting chars so `@café` does not split a codepoint.
+            while self.is_tag_name_character_at_cursor() {
+                self.advance_cursor();
             }
 
             return Token::Tag(TokenTag {
-                text: self.source[name_start..(name_start + name_length)].to_string(),
+                text: self.source[name_start..self.cursor].to_string(),
             });
         }
     }
 
+    /// A ':' is only part of a tag name when a namespaced name continues after it (`@svg:rect`),
+    /// so `@b: text` is still the tag `b` followed by text.
+    fn is_tag_name_character_at_cursor(&self) -> bool {
+        return self.current_char().is_tag_name_character()
+            || (self.current_char() == ':' && self.char_at(self.cursor + 1).is_alphanumeric());
+    }
+
+    /// `<name` or `</name` in Text mode while `html_tags` is on, a lone `<` stays as text.
+    fn is_html_tag_start(&self) -> bool {
+        if !self.html_tags || self.mode != LexerMode::Text || self.current_char() != '<' {
+            return false;
+        }
+
+        let next_char = self.char_at(self.cursor + 1);
+
+        return next_char.is_ascii_alphabetic()
+            || (next_char == '/' && self.char_at(self.cursor + 2).is_ascii_alphabetic());
+    }
+
+    /// `>` or `/>` ending an html open tag's attribute list (lexed in Code mode).
+    fn is_html_tag_end(&self) -> bool {
+        if !self.html_tags || self.mode != LexerMode::Code {
+            return false;
+        }
+
+        return self.current_char() == '>'
+            || (self.current_char() == '/' && self.char_at(self.cursor + 1) == '>');
+    }
+
+    fn parse_html_tag_name(&mut self) -> Token {
+        self.advance_cursor(); // '<'
+
+        let is_close_tag = self.current_char() == '/';
+
+        if is_close_tag {
+            self.advance_cursor(); // '/'
+        }
+
+        let name_start = self.cursor;
+
+        while self.is_tag_name_character_at_cursor() {
+            self.advance_cursor();
+        }
+
+        let name = self.source[name_start..self.cursor].to_string();
+
+        if !is_close_tag {
+            return Token::HtmlOpenTag(TokenTag { text: name });
+        }
+
+        self.skip_whitespace();
+
+        if self.current_char() != '>' {
+            return Token::error(
+                ErrorCode::UnterminatedHtmlTag,
+                format!("Unterminated closing tag '</{}'", name),
+            );
+        }
+
+        self.advance_cursor(); // '>'
+
+        return Token::HtmlCloseTag(TokenTag { text: name });
+    }
+
+    /// The `{name}` of a `${name}` macro placeholder (the '$' was already read), which is kept as plain text.
+    /// Only while `macro_placeholders` is on.
+    fn placeholder_length_at_cursor(&self) -> Option<usize> {
+        if !self.macro_placeholders || self.current_char() != '{' {
+            return None;
+        }
+
+        let name_length = self.source[self.cursor + 1..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+            .count();
+
+        if name_length == 0 || self.char_at(self.cursor + 1 + name_length) != '}' {
+            return None;
+        }
+
+        return Some(name_length + 2);
+    }
+
+    /// A single '`' in Text mode while `inline_code_spans` is on, '``' is an escaped backtick.
+    fn is_code_span_start(&self) -> bool {
+        return self.inline_code_spans
+            && self.mode == LexerMode::Text
+            && self.current_char() == '`'
+            && self.char_at(self.cursor + 1) != '`';
+    }
+
+    /// The contents of a code span are kept as is other than '``' standing for a '`'.
+    fn parse_code_span(&mut self) -> Token {
+        self.advance_cursor(); // '`'
+
+        let mut code = String::new();
+
+        loop {
+            if self.is_at_end() {
+                return Token::error(
+                    ErrorCode::UnterminatedCodeSpan,
+                    "Unterminated code span".to_string(),
+                );
+            }
+
+            let c = self.current_char();
+            self.advance_cursor();
+
+            if c == '`' {
+                if self.current_char() != '`' {
+                    break;
+                }
+
+                self.advance_cursor(); // Second '`' of '``'
+            }
+
+            code.push(c);
+        }
+
+        return Token::CodeSpan(code);
+    }
+
     fn parse_text_block(&mut self) -> Token {
         let mut text_block = String::new();
         let line_no_start = self.line_no;
+        let column_no_start = self.column_no;
+        let offset_start = self.cursor;
         let mut line_no_with_content = line_no_start;
+        let mut content_end_offset = self.cursor;
+        let mut escaped_text_length = 0; // Trimming never removes an escaped character (`trail\ `).
 
         while self.is_not_at_end()
             && !self
                 .current_char()
                 .is_text_block_ending_character(self.mode)
-            && self.current_char() != '\"'
+            && (self.current_char() != '\"' || self.mode == LexerMode::Verbatim)
+            && !(self.is_html_tag_start() || self.is_html_tag_end() || self.is_code_span_start())
         {
-            if self.is_at_end() {
-                return Token::Error("Unterminated Text Block".to_string());
-            }
-
             let c = self.current_char();
             let c_was_newline = self.advance_cursor();
 
-            if c == '\\' {
-                let escaped_character = self.current_char();
-                self.advance_cursor();
+            if c == '\\' && self.mode == LexerMode::Verbatim {
+                // NOTE(SR): Only the structural characters need escaping, keep the backslash in `C:\path`.
+                if matches!(self.current_char(), '@' | '{' | '}' | '\\') {
+                    text_block.push(self.current_char());
+                    self.advance_cursor();
+                } else {
+                    text_block.push(c);
+                }
 
-                let actual_character = match escaped_character {
-                    'n' => '\n',
-                    'r' => '\r',
-                    't' => '\t',
-                    '\\' => '\\',
-                    '\'' => '\'',
-                    '\"' => '\"',
-                    _ => escaped_character,
-                };
+                line_no_with_content = self.line_no;
+                content_end_offset = self.cursor;
+            } else if c == '\\' {
+                if self.is_at_end() {
+                    return Token::error(
+                        ErrorCode::UnterminatedEscape,
+                        "Unterminated escape sequence at end of file".to_string(),
+                    );
+                }
 
-                text_block.push(actual_character);
+                match self.parse_escaped_character() {
+                    Ok(escaped_character) => text_block.push(escaped_character),
+                    Err(err_token) => return err_token,
+                }
+
+                escaped_text_length = text_block.len();
+                content_end_offset = self.cursor;
+            } else if let Some(placeholder_length) =
+                self.placeholder_length_at_cursor().filter(|_| c == '$')
+            {
+                text_block.push('$');
+
+                for _ in 0..placeholder_length {
+                    text_block.push(self.current_char());
+                    self.advance_cursor();
+                }
+
+                line_no_with_content = self.line_no;
+                content_end_offset = self.cursor;
+            } else if c == '`' && self.inline_code_spans && self.mode == LexerMode::Text {
+                self.advance_cursor(); // Second '`' of '``', a lone one would have ended the block.
+
+                text_block.push('`');
+                content_end_offset = self.cursor;
+            } else if c_was_newline && self.mode == LexerMode::Verbatim {
+                text_block.push('\n');
             } else if c_was_newline {
+                let line_no_after_newline = self.line_no;
+
                 self.skip_whitespace();
+
+                let is_blank_line = self.line_no != line_no_after_newline;
+
+                while self.is_comment_start() {
+                    if let Err(err_token) = self.skip_comment() {
+                        return err_token;
+                    }
+
+                    self.skip_whitespace();
+                }
+
+                if self.split_text_at_blank_lines && is_blank_line {
+                    break;
+                }
+
                 text_block.push(' ');
             } else {
                 text_block.push(c);
                 line_no_with_content = self.line_no;
+
+                if !c.is_ascii_whitespace() {
+                    content_end_offset = self.cursor;
+                }
             }
         }
 
+        self.text_end_offset = Some(content_end_offset);
+
+        let mut text = if self.mode == LexerMode::Verbatim {
+            text_block.clone()
+        } else {
+            let trimmed_length = text_block.trim_end().len().max(escaped_text_length);
+            text_block[..trimmed_length].to_string()
+        };
+
+        // NOTE(SR): Whitespace before a structural '{' / '}' is never significant but before an inline tag it is.
+        if self.keep_space_before_tag
+            && text.len() != text_block.len()
+            && self.is_not_at_end()
+            && (self.current_char() == '@' || self.is_code_span_start())
+        {
+            text.push(' ');
+        }
+
         return Token::Text(TokenText {
-            line_no_start: line_no_start,
+            line_no_start,
+            column_no_start,
+            offset_start,
             line_no_end_with_content: line_no_with_content,
             line_no_end: self.line_no,
-            text: text_block.trim_end().to_string(),
+            text,
         });
     }
 
@@ -265,10 +940,15 @@ impl Lexer {
     }
 
     fn advance_cursor(&mut self) -> bool {
-        let is_win_newline = self.current_char() == '\r';
-        let is_newline = self.current_char() == '\n';
+        if self.is_at_end() {
+            return false;
+        }
+
+        let c = self.current_char();
+        let is_win_newline = c == '\r';
+        let is_newline = c == '\n';
 
-        self.cursor += 1;
+        self.cursor += c.len_utf8();
 
         if is_win_newline || is_newline {
             if is_win_newline && self.current_char() == '\n' {
@@ -276,6 +956,9 @@ impl Lexer {
             }
 
             self.line_no += 1;
+            self.column_no = 1;
+        } else {
+            self.column_no += 1;
         }
 
         return is_win_newline || is_newline;
@@ -285,8 +968,14 @@ impl Lexer {
         return self.char_at(self.cursor);
     }
 
+    // NOTE(SR):
+    //   `index` is a byte offset, only decodes the one char rather than walking the whole string.
+    //   Reading past the end gives '\0' so scanning loops stop instead of panicking.
     fn char_at(&self, index: usize) -> char {
-        return self.source.chars().nth(index).unwrap();
+        return match self.source.get(index..) {
+            Some(rest) => rest.chars().next().unwrap_or('\0'),
+            None => '\0',
+        };
     }
 
     fn is_at_end(&self) -> bool {
@@ -298,11 +987,25 @@ impl Lexer {
     }
 }
 
+/// Yields every token up to (but not including) the `Token::EndOfFile`, errors are yielded like any other token.
+impl Iterator for Lexer {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return match self.get_next_token() {
+            Token::EndOfFile() => None,
+            token => Some(token),
+        };
+    }
+}
+
 // Character Helpers
 
 trait CharExt {
     fn is_special_character(&self, mode: LexerMode) -> bool;
     fn is_text_block_ending_character(&self, mode: LexerMode) -> bool;
+    fn unescaped(&self) -> char;
+    fn is_tag_name_character(&self) -> bool;
 }
 
 impl CharExt for char {
@@ -313,17 +1016,756 @@ impl CharExt for char {
             || *self == '('
             || *self == ')'
             || *self == '='
-            || (*self == ',' && mode == LexerMode::Code);
+            || ((*self == ',' || *self == '[' || *self == ']') && mode == LexerMode::Code);
     }
 
     fn is_text_block_ending_character(&self, mode: LexerMode) -> bool {
-        if *self == '@' || *self == '{' || *self == '}' || *self == '=' {
+        if *self == '@' || *self == '{' || *self == '}' {
+            return true;
+        }
+
+        if *self == '=' && mode != LexerMode::Verbatim {
             return true;
         }
 
         match mode {
-            LexerMode::Text => return false,
-            LexerMode::Code => return *self == ' ',
+            LexerMode::Text | LexerMode::Verbatim => return false,
+            LexerMode::Code => {
+                return self.is_ascii_whitespace()
+                    || *self == '('
+                    || *self == ')'
+                    || *self == ','
+                    || *self == '['
+                    || *self == ']'
+            }
+        }
+    }
+
+    /// Letters, digits, '_' and '-' (for custom element style names like `my-widget`).
+    fn is_tag_name_character(&self) -> bool {
+        return self.is_alphanumeric() || *self == '_' || *self == '-';
+    }
+
+    /// The character an escape sequence (`\` followed by `self`) stands for.
+    fn unescaped(&self) -> char {
+        return match *self {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            _ => *self,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_tokens(source: &str, comments: bool) -> Vec<String> {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.comments = comments;
+
+        let mut texts = Vec::new();
+
+        loop {
+            match lexer.get_next_token() {
+                Token::Text(text) => texts.push(text.text),
+                Token::EndOfFile() => break,
+                _ => {}
+            }
+        }
+
+        return texts;
+    }
+
+    /// Every token up to the end of the file, written with `Token`'s `Display`.
+    fn token_strings(mut lexer: Lexer) -> Vec<String> {
+        return lexer.by_ref().map(|token| token.to_string()).collect();
+    }
+
+    fn tokens(source: &str) -> Vec<String> {
+        return token_strings(Lexer::new(source.to_string()));
+    }
+
+    /// Each token with the `(line, column)` it started at.
+    fn token_positions(source: &str) -> Vec<(String, usize, usize)> {
+        let mut lexer = Lexer::new(source.to_string());
+        let mut positions = Vec::new();
+
+        loop {
+            let token = lexer.get_next_token();
+
+            if token == Token::EndOfFile() {
+                return positions;
+            }
+
+            positions.push((
+                token.to_string(),
+                lexer.token_line_no,
+                lexer.token_column_no,
+            ));
         }
     }
+
+    /// The slice of `source` each token's span covers, paired with the token.
+    fn token_slices(source: &str) -> Vec<(String, &str)> {
+        let mut lexer = Lexer::new(source.to_string());
+        let mut slices = Vec::new();
+
+        loop {
+            let spanned = lexer.get_next_spanned_token();
+
+            if spanned.value == Token::EndOfFile() {
+                assert_eq!(spanned.start_offset, source.len());
+                return slices;
+            }
+
+            slices.push((
+                spanned.value.to_string(),
+                &source[spanned.start_offset..spanned.end_offset],
+            ));
+        }
+    }
+
+    /// Tokens lexed the way an attribute list is, in `LexerMode::Code`.
+    fn code_tokens(source: &str) -> Vec<String> {
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.push_mode(LexerMode::Code);
+
+        return token_strings(lexer);
+    }
+
+    #[test]
+    fn false_is_a_bool_literal() {
+        assert_eq!(
+            tokens("@tag(x=false)"),
+            [
+                "tag '@tag'",
+                "'('",
+                "text 'x'",
+                "'='",
+                "bool 'false'",
+                "')'"
+            ]
+        );
+        assert_eq!(tokens("false"), ["bool 'false'"]);
+        assert_eq!(tokens("false}"), ["bool 'false'", "'}'"]);
+    }
+
+    #[test]
+    fn bool_literal_covers_the_whole_keyword() {
+        let mut lexer = Lexer::new("false}".to_string());
+        let token = lexer.get_next_spanned_token();
+
+        assert_eq!(token.value, Token::BoolLiteral(false));
+        assert_eq!((token.start_offset, token.end_offset), (0, 5));
+        assert_eq!(lexer.get_next_token(), Token::CloseBrace);
+    }
+
+    #[test]
+    fn comments_are_text_unless_enabled() {
+        assert_eq!(
+            text_tokens("@text{// Not a comment}", false),
+            ["// Not a comment"]
+        );
+    }
+
+    #[test]
+    fn comments_inside_of_a_sentence_are_text() {
+        assert_eq!(
+            text_tokens("@text{A // not a comment} @text{B /* nor this */ C}", true),
+            ["A // not a comment", "B /* nor this */ C"]
+        );
+    }
+
+    #[test]
+    fn comments_between_tags_and_at_line_start_are_skipped() {
+        let source = "// Line\n@text{\n  /* Block */ First\n  // Line\n  Second\n} /* Block */";
+
+        assert_eq!(text_tokens(source, true), ["First Second"]);
+    }
+
+    #[test]
+    fn only_whole_keywords_are_bools() {
+        assert_eq!(tokens("trueish"), ["text 'trueish'"]);
+        assert_eq!(tokens("falsey"), ["text 'falsey'"]);
+        assert_eq!(
+            tokens("true falsehood"),
+            ["bool 'true'", "text 'falsehood'"]
+        );
+        assert_eq!(
+            tokens("@t(a=true b=false)"),
+            [
+                "tag '@t'",
+                "'('",
+                "text 'a'",
+                "'='",
+                "bool 'true'",
+                "text 'b'",
+                "'='",
+                "bool 'false'",
+                "')'"
+            ]
+        );
+    }
+
+    #[test]
+    fn keywords_are_case_sensitive() {
+        assert_eq!(tokens("True"), ["text 'True'"]);
+        assert_eq!(tokens("FALSE"), ["text 'FALSE'"]);
+    }
+
+    #[test]
+    fn large_multibyte_input_lexes_every_tag() {
+        let line_count = 2_000;
+        let source: String = (0..line_count)
+            .map(|i| format!("@p{{café ☕ line {}}}\n", i))
+            .collect();
+        let mut lexer = Lexer::new(source.clone());
+        let mut tag_count = 0;
+        let mut last_text = String::new();
+
+        loop {
+            let token = lexer.get_next_spanned_token();
+
+            match token.value {
+                Token::Tag(_) => tag_count += 1,
+                Token::Text(text) => {
+                    assert_eq!(&source[token.start_offset..token.end_offset], text.text);
+                    last_text = text.text;
+                }
+                Token::EndOfFile() => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(tag_count, line_count);
+        assert_eq!(last_text, "café ☕ line 1999");
+        assert_eq!(lexer.token_line_no, line_count + 1);
+    }
+
+    #[test]
+    fn end_of_file_is_returned_again_on_every_read() {
+        for source in ["", "@tag", "text", "@tag(x=1", "\"unterminated"] {
+            let mut lexer = Lexer::new(source.to_string());
+
+            while lexer.get_next_token() != Token::EndOfFile() {}
+
+            for _ in 0..3 {
+                assert_eq!(lexer.get_next_token(), Token::EndOfFile(), "{:?}", source);
+                assert_eq!(lexer.offset(), source.len());
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_input_is_an_error_token() {
+        assert_eq!(
+            tokens("@tag(x=\"unterminated"),
+            [
+                "tag '@tag'",
+                "'('",
+                "text 'x'",
+                "'='",
+                "error (Unterminated string literal starting on line 1)"
+            ]
+        );
+        assert_eq!(
+            tokens("@\"abc"),
+            ["error (Unterminated string literal starting on line 1)"]
+        );
+        assert_eq!(
+            tokens("end\\"),
+            ["error (Unterminated escape sequence at end of file)"]
+        );
+        assert_eq!(tokens("123."), ["number '123.'"]);
+    }
+
+    #[test]
+    fn quoted_tag_names_use_string_escapes() {
+        assert_eq!(tokens(r#"@"say \"hi\"""#), ["tag '@say \"hi\"'"]);
+        assert_eq!(
+            tokens(r#"@"two\nlines"{x}"#),
+            ["tag '@two\nlines'", "'{'", "text 'x'", "'}'"]
+        );
+        assert_eq!(tokens(r#"@"back\\slash""#), ["tag '@back\\slash'"]);
+    }
+
+    #[test]
+    fn numbers_have_at_most_one_decimal_point() {
+        assert_eq!(
+            code_tokens("1.2.3"),
+            ["error (Malformed number '1.2.3', only one decimal point is allowed)"]
+        );
+        assert_eq!(
+            code_tokens("1..2"),
+            ["error (Malformed number '1..2', only one decimal point is allowed)"]
+        );
+        assert_eq!(code_tokens("5. 0.5"), ["number '5.'", "number '0.5'"]);
+
+        let mut lexer = Lexer::new("1.2.3".to_string());
+        assert!(matches!(
+            lexer.get_next_token(),
+            Token::Error(TokenError {
+                code: ErrorCode::MalformedNumber,
+                ..
+            })
+        ));
+        assert_eq!(lexer.get_next_token(), Token::EndOfFile());
+    }
+
+    #[test]
+    fn columns_restart_on_every_line() {
+        assert_eq!(
+            token_positions("@a{x}\n  @bé{y} \"s\"\r\n\t@c"),
+            [
+                ("tag '@a'".to_string(), 1, 1),
+                ("'{'".to_string(), 1, 3),
+                ("text 'x'".to_string(), 1, 4),
+                ("'}'".to_string(), 1, 5),
+                ("tag '@bé'".to_string(), 2, 3),
+                ("'{'".to_string(), 2, 6),
+                ("text 'y'".to_string(), 2, 7),
+                ("'}'".to_string(), 2, 8),
+                ("string \"s\"".to_string(), 2, 10),
+                ("tag '@c'".to_string(), 3, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_blocks_record_their_starting_column() {
+        let mut lexer = Lexer::new("@p{\n   ünïcode text}".to_string());
+
+        lexer.get_next_token(); // '@p'
+        lexer.get_next_token(); // '{'
+
+        match lexer.get_next_token() {
+            Token::Text(text) => {
+                assert_eq!(text.text, "ünïcode text");
+                assert_eq!((text.line_no_start, text.column_no_start), (2, 4));
+                assert_eq!(text.offset_start, 7);
+            }
+            token => panic!("Expected text but got {}", token),
+        }
+    }
+
+    #[test]
+    fn spans_cover_the_source_they_were_lexed_from() {
+        assert_eq!(
+            token_slices("@tâg(k=\"a\\\"b\")\n{ü \\@x\n  y}"),
+            [
+                ("tag '@tâg'".to_string(), "@tâg"),
+                ("'('".to_string(), "("),
+                ("text 'k'".to_string(), "k"),
+                ("'='".to_string(), "="),
+                ("string \"a\"b\"".to_string(), "\"a\\\"b\""),
+                ("')'".to_string(), ")"),
+                ("'{'".to_string(), "{"),
+                ("text 'ü @x y'".to_string(), "ü \\@x\n  y"),
+                ("'}'".to_string(), "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn hex_binary_and_exponent_numbers() {
+        let values: Vec<f64> = ["0xFF", "0b101", "1e3", "1.5E-2", "2.5e+1"]
+            .iter()
+            .map(|source| {
+                let mut lexer = Lexer::new(source.to_string());
+                lexer.push_mode(LexerMode::Code);
+
+                return match lexer.get_next_token() {
+                    Token::NumberLiteral(number) => number.value,
+                    token => panic!("'{}' lexed as {}", source, token),
+                };
+            })
+            .collect();
+
+        assert_eq!(values, [255.0, 5.0, 1000.0, 0.015, 25.0]);
+        assert_eq!(
+            code_tokens("@t(x=0x1F y=1e3)"),
+            [
+                "tag '@t'",
+                "'('",
+                "text 'x'",
+                "'='",
+                "number '0x1F'",
+                "text 'y'",
+                "'='",
+                "number '1e3'",
+                "')'",
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_hex_and_exponent_numbers_are_errors() {
+        assert_eq!(
+            code_tokens("0x"),
+            ["error (Malformed number '0x', expected base 16 digits after the prefix)"]
+        );
+        assert_eq!(
+            code_tokens("0b"),
+            ["error (Malformed number '0b', expected base 2 digits after the prefix)"]
+        );
+        assert_eq!(
+            code_tokens("1e"),
+            ["error (Malformed number '1e', expected digits after the exponent)"]
+        );
+        assert_eq!(
+            code_tokens("1e+ 2"),
+            [
+                "error (Malformed number '1e+', expected digits after the exponent)",
+                "number '2'",
+            ]
+        );
+
+        // NOTE(SR): A word straight after a number is not an exponent.
+        assert_eq!(code_tokens("5em"), ["number '5'", "text 'em'"]);
+    }
+
+    #[test]
+    fn signs_are_part_of_numbers_only_in_attribute_lists() {
+        assert_eq!(
+            code_tokens("x=-5 y=-2.75 z=+2 w=-x"),
+            [
+                "text 'x'",
+                "'='",
+                "number '-5'",
+                "text 'y'",
+                "'='",
+                "number '-2.75'",
+                "text 'z'",
+                "'='",
+                "number '+2'",
+                "text 'w'",
+                "'='",
+                "text '-x'",
+            ]
+        );
+        assert_eq!(
+            tokens("well-known -5 degrees"),
+            ["text 'well-known -5 degrees'"]
+        );
+
+        let tag = match *crate::parser::parse("@t(x=-5 y=-2.75){a -5 b}")
+            .ok()
+            .unwrap()
+        {
+            crate::ast::ASTNode::Root(root) => root.find_first_tag("t").unwrap().clone(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(tag.find_attribute_f64("x"), Some(-5.0));
+        assert_eq!(tag.find_attribute_f64("y"), Some(-2.75));
+    }
+
+    #[test]
+    fn peeking_does_not_consume_the_token() {
+        let mut lexer = Lexer::new("@a{x}\n@b".to_string());
+
+        assert_eq!(lexer.peek_token().to_string(), "tag '@a'");
+        assert_eq!(lexer.peek_token().to_string(), "tag '@a'");
+        assert_eq!(lexer.get_next_token().to_string(), "tag '@a'");
+
+        assert_eq!(lexer.get_next_token(), Token::OpenBrace);
+        assert_eq!(lexer.get_next_token().to_string(), "text 'x'");
+        assert_eq!(lexer.get_next_token(), Token::CloseBrace);
+
+        let peeked = lexer.peek_token().clone();
+        let peeked_position = (lexer.token_line_no, lexer.token_column_no);
+
+        assert_eq!(peeked.to_string(), "tag '@b'");
+        assert_eq!(lexer.get_next_token(), peeked);
+        assert_eq!(
+            (lexer.token_line_no, lexer.token_column_no),
+            peeked_position
+        );
+        assert_eq!(peeked_position, (2, 1));
+        assert_eq!(lexer.peek_token(), &Token::EndOfFile());
+        assert_eq!(lexer.get_next_token(), Token::EndOfFile());
+    }
+
+    #[test]
+    fn multibyte_characters_lex_whole() {
+        assert_eq!(
+            tokens("@café(k=\"naïve 🎉\"){crème brûlée 🍮} @\"🎉\""),
+            [
+                "tag '@café'",
+                "'('",
+                "text 'k'",
+                "'='",
+                "string \"naïve 🎉\"",
+                "')'",
+                "'{'",
+                "text 'crème brûlée 🍮'",
+                "'}'",
+                "tag '@🎉'",
+            ]
+        );
+        assert_eq!(
+            code_tokens("k=\"naïve 🎉\""),
+            ["text 'k'", "'='", "string \"naïve 🎉\""]
+        );
+    }
+
+    #[test]
+    fn raw_strings_keep_their_content_as_written() {
+        let raw = "  fn main() {\n\n      println!(\"\\n\");\n  }\n";
+        let mut lexer = Lexer::new(format!("@code{{\"\"\"{}\"\"\"}}", raw));
+
+        assert_eq!(lexer.get_next_token().to_string(), "tag '@code'");
+        assert_eq!(lexer.get_next_token(), Token::OpenBrace);
+        assert_eq!(
+            lexer.get_next_token(),
+            Token::StringLiteral(raw.to_string())
+        );
+        assert_eq!(lexer.get_next_token(), Token::CloseBrace);
+        assert_eq!(lexer.get_next_token(), Token::EndOfFile());
+    }
+
+    #[test]
+    fn unterminated_raw_string_is_an_error() {
+        assert_eq!(
+            tokens("\"\"\"never closed\\ \"\""),
+            ["error (Unterminated raw string)"]
+        );
+    }
+
+    #[test]
+    fn punctuation_has_named_tokens() {
+        let mut lexer = Lexer::new("@t(x=1){y}".to_string());
+        let mut token_list = Vec::new();
+
+        while lexer.peek_token() != &Token::EndOfFile() {
+            token_list.push(lexer.get_next_token());
+        }
+
+        assert!(token_list.contains(&Token::OpenParen));
+        assert!(token_list.contains(&Token::Equals));
+        assert!(token_list.contains(&Token::CloseParen));
+        assert!(token_list.contains(&Token::OpenBrace));
+        assert!(token_list.contains(&Token::CloseBrace));
+        assert!(!token_list
+            .iter()
+            .any(|token| matches!(token, Token::Character(_))));
+        assert_eq!(Token::Equals.kind_name(), "'='");
+    }
+
+    #[test]
+    fn code_spans_are_lexed_only_when_enabled() {
+        let source = "Run `x = {1}` or `a``b` now";
+
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.inline_code_spans = true;
+
+        assert_eq!(
+            token_strings(lexer),
+            [
+                "text 'Run'",
+                "code span `x = {1}`",
+                "text 'or'",
+                "code span `a`b`",
+                "text 'now'",
+            ]
+        );
+        assert_eq!(tokens("Run `code` now"), ["text 'Run `code` now'"]);
+
+        let mut lexer = Lexer::new("`never closed".to_string());
+        lexer.inline_code_spans = true;
+
+        assert_eq!(token_strings(lexer), ["error (Unterminated code span)"]);
+    }
+
+    #[test]
+    fn iterator_stops_at_the_end_of_the_file_and_yields_errors() {
+        let mut lexer = Lexer::new("@a{x} \"open".to_string());
+
+        assert_eq!(
+            lexer
+                .by_ref()
+                .filter(|token| matches!(token, Token::Tag(_) | Token::Error(_)))
+                .map(|token| token.kind_name())
+                .collect::<Vec<_>>(),
+            ["a tag", "an error"]
+        );
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.next(), None);
+
+        let tag_count = Lexer::new("@a @b{@c} text @d".to_string())
+            .take_while(|token| *token != Token::CloseBrace)
+            .filter(|token| matches!(token, Token::Tag(_)))
+            .count();
+
+        assert_eq!(tag_count, 3);
+    }
+
+    #[test]
+    fn tag_names_may_contain_hyphens() {
+        assert_eq!(
+            tokens("@my-widget{x} @x-y-z @\"my widget\""),
+            [
+                "tag '@my-widget'",
+                "'{'",
+                "text 'x'",
+                "'}'",
+                "tag '@x-y-z'",
+                "tag '@my widget'",
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_names_cannot_start_with_a_hyphen() {
+        assert_eq!(
+            tokens("@-widget"),
+            [
+                "error (Tag names can not start with a '-')",
+                "text '-widget'",
+            ]
+        );
+    }
+
+    #[test]
+    fn verbatim_mode_keeps_newlines_and_runs_of_spaces() {
+        let source = "  line 1\n\n    line  2\n";
+
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.push_mode(LexerMode::Verbatim);
+
+        assert_eq!(
+            lexer.get_next_token().to_string(),
+            format!("text '{}'", source)
+        );
+        assert_eq!(lexer.get_next_token(), Token::EndOfFile());
+
+        assert_eq!(tokens(source), ["text 'line 1 line  2'"]);
+    }
+
+    #[test]
+    fn numbers_take_a_unit_only_when_enabled() {
+        let numbers = |numbers_with_units: bool| {
+            let mut lexer = Lexer::new("50% 3fr 1.5em 2".to_string());
+            lexer.push_mode(LexerMode::Code);
+            lexer.numbers_with_units = numbers_with_units;
+
+            return lexer
+                .map(|token| match token {
+                    Token::NumberLiteral(number) => (number.value, number.unit),
+                    token => panic!("Expected a number but got {}", token),
+                })
+                .collect::<Vec<_>>();
+        };
+
+        assert_eq!(
+            numbers(true),
+            [
+                (50.0, Some("%".to_string())),
+                (3.0, Some("fr".to_string())),
+                (1.5, Some("em".to_string())),
+                (2.0, None),
+            ]
+        );
+        assert_eq!(
+            code_tokens("50% 3fr"),
+            ["number '50'", "text '%'", "number '3'", "text 'fr'"]
+        );
+    }
+
+    #[test]
+    fn unterminated_string_reports_the_line_it_started_on() {
+        let source = "@title{Post}\n@image(Src=\"cover.png)\n\nline 4\nline 5\n";
+        let mut lexer = Lexer::new(source.to_string());
+        lexer.push_mode(LexerMode::Code);
+
+        let error = lexer
+            .by_ref()
+            .find(|token| matches!(token, Token::Error(_)))
+            .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "error (Unterminated string literal starting on line 2)"
+        );
+        assert_eq!(lexer.token_line_no, 2);
+
+        let errors = crate::parser::parse(source).err().unwrap().errors;
+
+        assert_eq!(errors[0].code, ErrorCode::UnterminatedString);
+        assert_eq!((errors[0].line_number, errors[0].column_number), (2, 12));
+    }
+
+    #[test]
+    fn unicode_escapes_in_text_and_strings() {
+        assert_eq!(tokens("smile \\u{1F600} \\u{41}"), ["text 'smile 😀 A'"]);
+        assert_eq!(
+            code_tokens("x=\"\\u{1F600}\\u{41}\""),
+            ["text 'x'", "'='", "string \"😀A\""]
+        );
+        assert_eq!(
+            tokens("\\u{ZZ}"),
+            [
+                "error (Malformed unicode escape, expected hex digits between '\\u{' and '}')",
+                "text 'ZZ'",
+                "'}'",
+            ]
+        );
+        assert_eq!(
+            tokens("\\u{D800}")[0],
+            "error ('\\u{D800}' is not a valid unicode codepoint)"
+        );
+    }
+
+    #[test]
+    fn namespaced_tag_and_attribute_names() {
+        assert_eq!(
+            tokens("@svg:rect @xlink:use"),
+            ["tag '@svg:rect'", "tag '@xlink:use'"]
+        );
+        assert_eq!(
+            code_tokens("xlink:href=\"#a\""),
+            ["text 'xlink:href'", "'='", "string \"#a\""]
+        );
+    }
+
+    #[test]
+    fn escaped_space_is_part_of_a_code_mode_word() {
+        assert_eq!(
+            code_tokens("Target=new\\ tab Size=2"),
+            [
+                "text 'Target'",
+                "'='",
+                "text 'new tab'",
+                "text 'Size'",
+                "'='",
+                "number '2'",
+            ]
+        );
+        assert_eq!(code_tokens("new tab"), ["text 'new'", "text 'tab'"]);
+
+        let mut parser = crate::parser::Parser::new("@link(Target=new\\ tab){x}".to_string());
+        parser.bareword_values_as_strings = true;
+
+        let tag = match *parser.parse().ok().unwrap() {
+            crate::ast::ASTNode::Root(root) => root.find_first_tag("link").unwrap().clone(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(tag.find_attribute_str("Target"), Some("new tab"));
+    }
+
+    #[test]
+    fn macro_placeholders_are_text_only_when_enabled() {
+        assert_eq!(
+            tokens("Hi ${who}!"),
+            ["text 'Hi $'", "'{'", "text 'who'", "'}'", "text '!'"]
+        );
+
+        let mut lexer = Lexer::new("Hi ${who}!".to_string());
+        lexer.macro_placeholders = true;
+
+        assert_eq!(token_strings(lexer), ["text 'Hi ${who}!'"]);
+    }
 }
@@ -4,10 +4,40 @@
 
 pub mod ast_processor;
 pub use ast_processor::visit_ast;
+pub use ast_processor::visit_ast_mut;
+pub use ast_processor::visit_ast_with;
+pub use ast_processor::visit_ast_with_context;
 pub use ast_processor::ASTProcessorVisitResult;
+pub use ast_processor::IASTMutProcessor;
 pub use ast_processor::IASTProcessor;
+pub use ast_processor::ProcessorContext;
+
+pub mod debug_processor;
+pub use debug_processor::DebugProcessor;
+
+pub mod markdown_processor;
+pub use markdown_processor::MarkdownProcessor;
+
+pub mod json_processor;
+pub use json_processor::JsonProcessor;
+
+pub mod json_lines_processor;
+pub use json_lines_processor::JsonLinesProcessor;
+
+pub mod html_stream;
+pub use html_stream::render_html_streaming;
+pub use html_stream::HtmlStreamOptions;
+pub use html_stream::HtmlStreamProcessor;
+pub use html_stream::StreamRenderError;
+
+mod json;
+
+pub mod ast_events;
+pub use ast_events::events;
+pub use ast_events::AstEvent;
 
 pub mod ast;
+pub use ast::ast_eq_ignoring_spans;
 pub use ast::ASTNode;
 pub use ast::ASTNodeList;
 pub use ast::ASTNodeLiteral;
@@ -15,9 +45,61 @@ pub use ast::ASTNodePtr;
 pub use ast::ASTNodeRoot;
 pub use ast::ASTNodeTag;
 pub use ast::ASTNodeText;
+pub use ast::AttributeMap;
+pub use ast::FromLiteral;
+pub use ast::Span;
+
+pub mod ast_stats;
+pub use ast_stats::ast_stats;
+pub use ast_stats::AstStats;
+
+pub mod ast_transform;
+pub use ast_transform::collapse_redundant_wrappers;
+pub use ast_transform::expand_macros;
+pub use ast_transform::MacroExpansionError;
 
 pub mod lexer;
+pub use lexer::Lexer;
+pub use lexer::LexerMode;
+pub use lexer::Spanned;
+pub use lexer::Token;
+pub use lexer::TokenNumber;
+pub use lexer::TokenTag;
+pub use lexer::TokenText;
+
+pub mod brace_check;
+pub use brace_check::check_brace_balance;
+
+pub mod indent_check;
+pub use indent_check::check_mixed_indentation;
+
+pub mod required_attributes;
+pub use required_attributes::check_required_attributes;
+pub use required_attributes::MissingAttribute;
+pub use required_attributes::RequiredAttributes;
+
+pub mod error_code;
+pub use error_code::ErrorCode;
+
+#[cfg(feature = "arena")]
+pub mod arena;
+#[cfg(feature = "arena")]
+pub use arena::ArenaNode;
+#[cfg(feature = "arena")]
+pub use arena::AstArena;
 
 pub mod parser;
+pub use parser::parse;
+pub use parser::ParseError;
+pub use parser::ParseErrors;
 pub use parser::ParseResult;
 pub use parser::Parser;
+
+pub mod semantic;
+pub use semantic::classify_tag;
+pub use semantic::SemanticTag;
+
+pub mod query;
+
+pub mod serialize;
+pub use serialize::to_srmark_string;
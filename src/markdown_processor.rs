@@ -0,0 +1,362 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   markdown_processor.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeRoot;
+use crate::ast::ASTNodeTag;
+use crate::ast::ASTNodeText;
+use crate::ast_processor::ASTProcessorVisitResult;
+use crate::ast_processor::IASTProcessor;
+use crate::semantic::classify_tag;
+use crate::semantic::SemanticTag;
+
+/// Converts a document into Markdown, accumulated into `output`.
+///
+/// Headings, paragraphs, links, images, (nested) lists, `@code` blocks and
+/// `@b` / `@i` / `@s` emphasis are mapped to their Markdown spelling, any other tag
+/// just writes out its contents.
+///
+/// Inline content is written with the spaces of its text nodes, parse with
+/// `keep_space_before_tag` and `keep_space_after_tag` so those match the source
+/// (`@b{un}believable` stays one word).
+///
+/// ```
+/// let mut parser = srmarkup::Parser::new(
+///     "@h1{Title} @text{Some @b{bold} text.} @ulist{ @listitem{One} @listitem{Two @ulist{ @listitem{Nested} }} }"
+///         .to_string(),
+/// );
+/// parser.keep_space_before_tag = true;
+/// parser.keep_space_after_tag = true;
+///
+/// let root = parser.parse().ok().unwrap();
+/// let mut markdown = srmarkup::MarkdownProcessor::new();
+///
+/// srmarkup::visit_ast(&root, &mut markdown);
+///
+/// assert_eq!(
+///     markdown.output,
+///     "# Title\n\nSome **bold** text.\n\n- One\n- Two\n  - Nested\n"
+/// );
+/// ```
+pub struct MarkdownProcessor {
+    pub output: String,
+    lists: Vec<ListState>, // Innermost list last.
+}
+
+struct ListState {
+    ordered: bool,
+    item_count: usize,
+    marker_width: usize, // Indent the items of a nested list need to line up under this list's text.
+}
+
+impl MarkdownProcessor {
+    pub fn new() -> Self {
+        MarkdownProcessor {
+            output: String::new(),
+            lists: Vec::new(),
+        }
+    }
+
+    // NOTE(SR): Blocks are separated by a blank line, except inside of a list item
+    //           where a paragraph just continues the item's line.
+    fn begin_block(&mut self) {
+        if self.lists.is_empty() {
+            self.end_line();
+
+            if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+                self.output.push('\n');
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        while self.output.ends_with(' ') {
+            self.output.pop();
+        }
+
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    // NOTE(SR): Inline content outside of any block (e.g. a lone `@image`) starts its own paragraph.
+    fn begin_inline(&mut self) {
+        if self.output.ends_with('\n') {
+            self.begin_block();
+        }
+    }
+
+    fn write_inline(&mut self, text: &str) {
+        self.begin_inline();
+
+        self.output.push_str(text);
+    }
+
+    // NOTE(SR): Nothing is added between inline nodes, the spaces come from the text as written.
+    //           Runs of whitespace become one space (a newline would end the heading / list item)
+    //           and whitespace at the start of a line is dropped.
+    fn write_text(&mut self, text: &str) {
+        let mut collapsed = String::with_capacity(text.len());
+
+        for c in text.chars() {
+            if !c.is_whitespace() {
+                collapsed.push(c);
+            } else if !collapsed.ends_with(' ') {
+                collapsed.push(' ');
+            }
+        }
+
+        let at_line_start = self.output.is_empty() || self.output.ends_with(char::is_whitespace);
+        let text = if at_line_start {
+            collapsed.trim_start()
+        } else {
+            collapsed.as_str()
+        };
+
+        if !text.is_empty() {
+            self.write_inline(&escape_markdown(text));
+        }
+    }
+
+    fn write_open_marker(&mut self, marker: &str) {
+        self.write_inline(marker);
+    }
+
+    fn write_close_marker(&mut self, marker: &str) {
+        self.output.push_str(marker);
+    }
+
+    fn emphasis_marker(tag_name: &str) -> Option<&'static str> {
+        return match tag_name.to_lowercase().as_str() {
+            "b" | "strong" => Some("**"),
+            "i" | "em" => Some("*"),
+            "s" | "del" => Some("~~"),
+            _ => None,
+        };
+    }
+
+    fn attribute_string(tag_node: &ASTNodeTag, key: &str) -> String {
+        return match tag_node.find_attribute_ci(key) {
+            Some(value) => value.to_string(),
+            None => String::new(),
+        };
+    }
+
+    fn write_code_block(&mut self, tag_node: &ASTNodeTag) {
+        let language = MarkdownProcessor::attribute_string(tag_node, "Lang");
+        let mut code = String::new();
+
+        for child in &tag_node.children {
+            if let ASTNode::Text(text_node) = child.as_ref() {
+                code.push_str(&text_node.text);
+            }
+        }
+
+        let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+
+        self.begin_block();
+        self.output.push_str("```");
+        self.output.push_str(&language);
+        self.output.push('\n');
+        self.output.push_str(code);
+        self.output.push_str("\n```\n");
+    }
+}
+
+impl Default for MarkdownProcessor {
+    fn default() -> Self {
+        return MarkdownProcessor::new();
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+
+    return result;
+}
+
+impl IASTProcessor for MarkdownProcessor {
+    fn visit_begin_tag(&mut self, tag_node: &ASTNodeTag, _: usize) -> ASTProcessorVisitResult {
+        if tag_node.text.eq_ignore_ascii_case("header") {
+            return ASTProcessorVisitResult::SkipChildren;
+        }
+
+        if tag_node.text.eq_ignore_ascii_case("code") {
+            self.write_code_block(tag_node);
+            return ASTProcessorVisitResult::SkipChildren;
+        }
+
+        if let Some(marker) = MarkdownProcessor::emphasis_marker(&tag_node.text) {
+            self.write_open_marker(marker);
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        match classify_tag(&tag_node.text) {
+            Some(SemanticTag::Heading(level)) => {
+                self.begin_block();
+                self.output.push_str(&"#".repeat(level as usize));
+                self.output.push(' ');
+            }
+            Some(SemanticTag::Paragraph) => {
+                self.begin_block();
+            }
+            Some(SemanticTag::UnorderedList) | Some(SemanticTag::OrderedList) => {
+                self.begin_block();
+                self.lists.push(ListState {
+                    ordered: classify_tag(&tag_node.text) == Some(SemanticTag::OrderedList),
+                    item_count: 0,
+                    marker_width: 2,
+                });
+            }
+            Some(SemanticTag::ListItem) => {
+                self.end_line();
+
+                let indent: usize = match self.lists.split_last() {
+                    Some((_, parents)) => parents.iter().map(|list| list.marker_width).sum(),
+                    None => 0,
+                };
+
+                let marker = match self.lists.last_mut() {
+                    Some(list) => {
+                        list.item_count += 1;
+
+                        let marker = if list.ordered {
+                            format!("{}. ", list.item_count)
+                        } else {
+                            "- ".to_string()
+                        };
+
+                        list.marker_width = marker.len();
+                        marker
+                    }
+                    None => "- ".to_string(),
+                };
+
+                self.output.push_str(&" ".repeat(indent));
+                self.output.push_str(&marker);
+            }
+            Some(SemanticTag::Link) => {
+                self.write_open_marker("[");
+            }
+            Some(SemanticTag::Image) => {
+                let alt = escape_markdown(&MarkdownProcessor::attribute_string(tag_node, "Alt"));
+                let src = MarkdownProcessor::attribute_string(tag_node, "Src");
+
+                self.write_inline(&format!("![{}]({})", alt, src));
+                return ASTProcessorVisitResult::SkipChildren;
+            }
+            None => {}
+        }
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_text(&mut self, text_node: &ASTNodeText, _: usize) -> ASTProcessorVisitResult {
+        self.write_text(&text_node.text);
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_literal(
+        &mut self,
+        literal_node: &ASTNodeLiteral,
+        _: usize,
+    ) -> ASTProcessorVisitResult {
+        self.write_inline(&escape_markdown(&literal_node.to_string()));
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_tag(&mut self, tag_node: &ASTNodeTag, _: usize) -> ASTProcessorVisitResult {
+        if let Some(marker) = MarkdownProcessor::emphasis_marker(&tag_node.text) {
+            self.write_close_marker(marker);
+            return ASTProcessorVisitResult::Continue;
+        }
+
+        match classify_tag(&tag_node.text) {
+            Some(SemanticTag::Heading(_)) | Some(SemanticTag::Paragraph)
+                if self.lists.is_empty() =>
+            {
+                self.end_line();
+            }
+            Some(SemanticTag::UnorderedList) | Some(SemanticTag::OrderedList) => {
+                self.lists.pop();
+                self.end_line();
+            }
+            Some(SemanticTag::Link) => {
+                let src = MarkdownProcessor::attribute_string(tag_node, "Src");
+                self.write_close_marker(&format!("]({})", src));
+            }
+            _ => {}
+        }
+
+        return ASTProcessorVisitResult::Continue;
+    }
+
+    fn visit_end_root(&mut self, _: &ASTNodeRoot, _: usize) -> ASTProcessorVisitResult {
+        self.end_line();
+        return ASTProcessorVisitResult::Continue;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_processor::visit_ast;
+    use crate::parser::Parser;
+
+    fn to_markdown(source: &str) -> String {
+        let mut parser = Parser::new(source.to_string());
+        parser.keep_space_before_tag = true;
+        parser.keep_space_after_tag = true;
+
+        let root = parser.parse().ok().unwrap();
+        let mut markdown = MarkdownProcessor::new();
+        visit_ast(&root, &mut markdown);
+
+        return markdown.output;
+    }
+
+    #[test]
+    fn document_keeps_the_spacing_of_its_source() {
+        let source = r#"
+@h1{2D Physics}
+
+@text{
+  Simply @b{un}believable, @i{really} @b{@i{very}} fast.
+  See @link(Src="https://example.com"){the docs} or
+  @image(Src="a.png" Alt="A")@image(Src="b.png" Alt="B").
+}
+
+@h2{Steps}
+@olist{
+  @listitem{Install @b{it}}
+  @listitem{Run it @ulist{ @listitem{Twice} }}
+}
+"#;
+
+        assert_eq!(
+            to_markdown(source),
+            concat!(
+                "# 2D Physics\n",
+                "\n",
+                "Simply **un**believable, *really* ***very*** fast. ",
+                "See [the docs](https://example.com) or ![A](a.png)![B](b.png).\n",
+                "\n",
+                "## Steps\n",
+                "\n",
+                "1. Install **it**\n",
+                "2. Run it\n",
+                "   - Twice\n",
+            )
+        );
+    }
+}
@@ -4,103 +4,370 @@
 //
 
 use crate::ast::ASTNode;
-use crate::ast::ASTNodeList;
 use crate::ast::ASTNodeLiteral;
 use crate::ast::ASTNodePtr;
 use crate::ast::ASTNodeRoot;
 use crate::ast::ASTNodeTag;
 use crate::ast::ASTNodeText;
+use crate::ast::Span;
+
+use crate::error_code::ErrorCode;
 
 use crate::lexer::Lexer;
 use crate::lexer::LexerMode;
 use crate::lexer::Token;
+use crate::lexer::TokenNumber;
 use crate::lexer::TokenTag;
 use crate::lexer::TokenText;
 
 pub struct ParseError {
+    pub code: ErrorCode,
     pub message: String,
     pub line_number: usize,
+    pub column_number: usize,
 }
 
 pub struct ParseErrors {
     pub errors: Vec<ParseError>,
+    /// Everything that could still be parsed, only filled in when `Parser::recover_from_errors` is set.
+    pub partial_root: Option<ASTNodePtr>,
+}
+
+impl ParseError {
+    /// Renders the error rustc style, the offending line of `source` with a `^` under the column:
+    ///
+    /// ```text
+    /// error: Expected '=' but got string "y", 'class' must be assigned to
+    ///  --> line 2, column 10
+    ///   |
+    /// 2 | @p(class "y"){ There }
+    ///   |          ^
+    /// ```
+    pub fn render(self: &Self, source: &str) -> String {
+        let line_label = self.line_number.to_string();
+        let gutter = " ".repeat(line_label.len());
+        let source_line = source
+            .lines()
+            .nth(self.line_number.saturating_sub(1))
+            .unwrap_or("");
+
+        // NOTE(SR): Tabs are kept in the caret's padding so it lines up however wide they are drawn.
+        let caret_padding: String = source_line
+            .chars()
+            .take(self.column_number.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        return format!(
+            "error: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}^\n",
+            self.message,
+            gutter,
+            self.line_number,
+            self.column_number,
+            gutter,
+            line_label,
+            source_line,
+            gutter,
+            caret_padding
+        );
+    }
+}
+
+impl ParseErrors {
+    /// Every error rendered with `ParseError::render`, separated by a blank line.
+    ///
+    /// ```
+    /// let source = "@p(class=\"x\"){ Hi }\n@p(class \"y\"){ There }";
+    /// let errors = srmarkup::parse(source).err().unwrap();
+    ///
+    /// assert_eq!(
+    ///     errors.render(source),
+    ///     concat!(
+    ///         "error: Expected '=' but got string \"y\", 'class' must be assigned to\n",
+    ///         " --> line 2, column 10\n",
+    ///         "  |\n",
+    ///         "2 | @p(class \"y\"){ There }\n",
+    ///         "  |          ^\n",
+    ///     )
+    /// );
+    /// ```
+    pub fn render(self: &Self, source: &str) -> String {
+        let rendered: Vec<String> = self.errors.iter().map(|err| err.render(source)).collect();
+
+        return rendered.join("\n");
+    }
 }
 
 pub type ParseResult = Result<ASTNodePtr, ParseErrors>;
 
+/// How the parser turns what it parsed into tree nodes, `Box`ed `ASTNode`s normally
+/// (or arena allocated ones with the `arena` feature).
+///
+/// Tags and the root are handed over without their children which are passed in separately.
+/// `open_root` / `open_tag` are called before any of the children are parsed, for builders that
+/// act on the nodes in document order instead of keeping them (`render_html_streaming`).
+pub(crate) trait TreeBuilder {
+    type Node;
+
+    fn open_root(&mut self, _root: &ASTNodeRoot) {}
+    fn open_tag(&mut self, _tag: &ASTNodeTag) {}
+
+    fn root(&mut self, root: ASTNodeRoot, children: Vec<Self::Node>) -> Self::Node;
+    fn tag(&mut self, tag: ASTNodeTag, children: Vec<Self::Node>) -> Self::Node;
+    fn text(&mut self, text: ASTNodeText) -> Self::Node;
+    fn literal(&mut self, literal: ASTNodeLiteral) -> Self::Node;
+}
+
+struct BoxTreeBuilder;
+
+impl TreeBuilder for BoxTreeBuilder {
+    type Node = ASTNodePtr;
+
+    fn root(&mut self, mut root: ASTNodeRoot, children: Vec<ASTNodePtr>) -> ASTNodePtr {
+        root.children = children;
+        return Box::new(ASTNode::Root(root));
+    }
+
+    fn tag(&mut self, mut tag: ASTNodeTag, children: Vec<ASTNodePtr>) -> ASTNodePtr {
+        tag.children = children;
+        return Box::new(ASTNode::Tag(tag));
+    }
+
+    fn text(&mut self, text: ASTNodeText) -> ASTNodePtr {
+        return Box::new(ASTNode::Text(text));
+    }
+
+    fn literal(&mut self, literal: ASTNodeLiteral) -> ASTNodePtr {
+        return Box::new(ASTNode::Literal(literal));
+    }
+}
+
+/// Parses `source` with the default options, use a `Parser` directly to turn any of them on.
+///
+/// ```
+/// let root = srmarkup::parse("@p(class=\"intro\"){ Hello }").ok().unwrap();
+///
+/// let classes = srmarkup::query::collect_attribute_values(&root, "class");
+/// assert_eq!(classes[0].to_string(), "intro");
+/// ```
+pub fn parse(source: &str) -> ParseResult {
+    return Parser::new(source.to_string()).parse();
+}
+
 /// Parses an sr-mark source text.
 pub struct Parser {
     lexer: Lexer,
     current_token: Token,
     error_log: Vec<ParseError>,
+    /// Allows unquoted identifiers as attribute values (`@link(Target=blank)`), stored as strings.
+    pub bareword_values_as_strings: bool,
+    /// Keeps the source text of number literals so `16.0` is not printed back as `16`.
+    pub preserve_number_text: bool,
+    /// Forwarded to `Lexer::keep_space_before_tag`.
+    pub keep_space_before_tag: bool,
+    /// Keeps the whitespace written after a tag or literal, as a leading space on the text
+    /// that follows (`@b{un} believable`) or a `" "` text node before the next tag.
+    /// Without whitespace they stay joined (`@b{un}believable`, `2D`).
+    pub keep_space_after_tag: bool,
+    /// Forwarded to `Lexer::comments`.
+    pub comments: bool,
+    /// Also accepts `<p class="x">hi</p>` and `<br/>`, producing the same nodes as `@p(class="x"){hi}` and `@br`.
+    pub html_style_tags: bool,
+    /// A tag without a `{}` body takes the text following it on the same line as its body (`@p Hello world`),
+    /// text blocks also end at blank lines so the body stops at the end of the paragraph.
+    pub implicit_body: bool,
+    /// Turns `` `code` `` inside of text into `@code{code}` (see `Lexer::inline_code_spans`).
+    pub inline_code_spans: bool,
+    /// Numbers directly followed by a unit (`50%`, `3fr`) become `ASTNodeLiteral::Measure`.
+    pub measure_literals: bool,
+    /// Bodies of these tags are lexed in `LexerMode::Verbatim` so their whitespace is kept as written (e.g. `pre`).
+    pub verbatim_tags: Vec<String>,
+    /// After a malformed attribute skip ahead to the next `)`, `{` or `}` instead of
+    /// reporting an error for every leftover token, and hand back the partial tree in `ParseErrors::partial_root`.
+    pub recover_from_errors: bool,
+    /// Stores attribute names lowercased so `Src` and `src` are the same attribute (and a duplicate if both are given).
+    pub lowercase_attribute_names: bool,
+    /// Forwarded to `Lexer::macro_placeholders`, needed by documents using `expand_macros`.
+    pub macro_placeholders: bool,
 }
 
 impl Parser {
     pub fn new(source: String) -> Self {
+        return Self::from_lexer(Lexer::new(source));
+    }
+
+    /// Parses the tokens of an already configured (or partially consumed) lexer.
+    /// Lexer options it has turned on stay on, the parser's own options are added on top.
+    pub fn from_lexer(lexer: Lexer) -> Self {
         Parser {
-            lexer: Lexer::new(source),
+            lexer,
             current_token: Token::EndOfFile(),
             error_log: Vec::new(),
+            bareword_values_as_strings: false,
+            preserve_number_text: false,
+            keep_space_before_tag: false,
+            keep_space_after_tag: false,
+            comments: false,
+            html_style_tags: false,
+            implicit_body: false,
+            inline_code_spans: false,
+            verbatim_tags: Vec::new(),
+            measure_literals: false,
+            recover_from_errors: false,
+            lowercase_attribute_names: false,
+            macro_placeholders: false,
         }
     }
 
     pub fn parse(&mut self) -> ParseResult {
+        let (root_node, error_log) = self.parse_tree(&mut BoxTreeBuilder);
+
+        return if error_log.is_empty() {
+            Ok(root_node)
+        } else {
+            Err(ParseErrors {
+                errors: error_log,
+                partial_root: if self.recover_from_errors {
+                    Some(root_node)
+                } else {
+                    None
+                },
+            })
+        };
+    }
+
+    /// The whole document built with `builder` along with any errors, the tree is partial if there are some.
+    pub(crate) fn parse_tree<B: TreeBuilder>(
+        &mut self,
+        builder: &mut B,
+    ) -> (B::Node, Vec<ParseError>) {
         let mut root_node = ASTNodeRoot {
             children: Vec::new(),
+            span: Span::new(1, 1),
         };
+        let mut children = Vec::new();
+
+        self.lexer.keep_space_before_tag |= self.keep_space_before_tag;
+        self.lexer.comments |= self.comments;
+        self.lexer.html_tags |= self.html_style_tags;
+        self.lexer.split_text_at_blank_lines |= self.implicit_body;
+        self.lexer.inline_code_spans |= self.inline_code_spans;
+        self.lexer.numbers_with_units |= self.measure_literals;
+        self.lexer.macro_placeholders |= self.macro_placeholders;
 
+        builder.open_root(&root_node);
         self.advance_token();
-        self.parse_impl(&mut root_node.children);
 
-        return if self.error_log.is_empty() {
-            Ok(Box::new(ASTNode::Root(root_node)))
-        } else {
-            Err(ParseErrors {
-                errors: std::mem::take(&mut self.error_log),
-            })
-        };
+        while self.current_token != Token::EndOfFile() {
+            self.parse_impl(builder, &mut children);
+            self.skip_unexpected_token("the document");
+        }
+
+        root_node.span.end_line = self.lexer.token_line_no;
+
+        return (
+            builder.root(root_node, children),
+            std::mem::take(&mut self.error_log),
+        );
     }
 
-    fn parse_impl(&mut self, parent_child_list: &mut ASTNodeList) {
+    fn parse_impl<B: TreeBuilder>(
+        &mut self,
+        builder: &mut B,
+        parent_child_list: &mut Vec<B::Node>,
+    ) {
+        let mut is_after_text = true; // Nothing before the first child needs separating from it.
+
         loop {
             let current_token = self.current_token.clone();
+            let is_spaced =
+                self.keep_space_after_tag && !is_after_text && self.lexer.token_after_whitespace;
+
+            // NOTE(SR): Text gets the space prepended instead, see `Token::Text` below.
+            if is_spaced
+                && matches!(
+                    current_token,
+                    Token::Tag(_)
+                        | Token::HtmlOpenTag(_)
+                        | Token::CodeSpan(_)
+                        | Token::StringLiteral(_)
+                        | Token::NumberLiteral(_)
+                        | Token::BoolLiteral(_)
+                )
+            {
+                let space_span = Span::at(
+                    self.lexer.token_line_no,
+                    self.lexer.token_column_no,
+                    self.lexer.token_offset(),
+                );
+                parent_child_list.push(builder.text(ASTNodeText::new(" ".to_string(), space_span)));
+            }
+
+            is_after_text = matches!(current_token, Token::Text(_));
 
             match current_token {
                 Token::Tag(ref tt) => {
-                    let tt_node = self.parse_tag_block(&tt);
-
-                    if tt_node.is_some() {
-                        parent_child_list.push(tt_node.unwrap());
+                    if let Some(tt_node) = self.parse_tag_block(builder, tt) {
+                        parent_child_list.push(tt_node);
                     }
                 }
+                Token::HtmlOpenTag(ref tt) => {
+                    let tt_node = self.parse_html_element(builder, tt);
+                    parent_child_list.push(tt_node);
+                }
                 Token::StringLiteral(ref str_lit) => {
-                    let child_node =
-                        Box::new(ASTNode::Literal(ASTNodeLiteral::Str(str_lit.clone())));
+                    let child_node = builder.literal(ASTNodeLiteral::Str(str_lit.clone()));
                     self.advance_token();
 
                     parent_child_list.push(child_node);
                 }
-                Token::NumberLiteral(number) => {
-                    let child_node = Box::new(ASTNode::Literal(ASTNodeLiteral::Float(number)));
+                Token::NumberLiteral(_) => {
+                    let child_node = builder.literal(self.token_to_ast_literal(current_token));
                     self.advance_token();
 
                     parent_child_list.push(child_node);
                 }
                 Token::BoolLiteral(value) => {
-                    let child_node = Box::new(ASTNode::Literal(ASTNodeLiteral::Bool(value)));
+                    let child_node = builder.literal(ASTNodeLiteral::Bool(value));
                     self.advance_token();
 
                     parent_child_list.push(child_node);
                 }
                 Token::Text(ref txt) => {
-                    let child_node = Box::new(ASTNode::Text(ASTNodeText {
-                        text: txt.text.clone(),
-                    }));
+                    let mut text_node = Self::make_text_node(txt);
+
+                    if is_spaced {
+                        text_node.text.insert(0, ' ');
+                    }
+
+                    let child_node = builder.text(text_node);
                     self.advance_token();
 
                     parent_child_list.push(child_node);
                 }
-                Token::Character(_value) => {
+                Token::CodeSpan(ref code) => {
+                    let code_span = Span::at(
+                        self.lexer.token_line_no,
+                        self.lexer.token_column_no,
+                        self.lexer.token_offset(),
+                    );
+                    let mut code_node = ASTNodeTag::new("code".to_string());
+                    code_node.span = code_span;
+                    builder.open_tag(&code_node);
+                    let code_text = builder.text(ASTNodeText::new(code.clone(), code_span));
+                    self.advance_token();
+
+                    parent_child_list.push(builder.tag(code_node, vec![code_text]));
+                }
+                Token::HtmlCloseTag(_) => {
+                    break;
+                }
+                Token::Equals
+                | Token::OpenParen
+                | Token::CloseParen
+                | Token::OpenBrace
+                | Token::CloseBrace
+                | Token::Character(_) => {
                     //let child_node = Box::new(ASTNode::Text(ASTNodeText {
                     //  text: value.to_string(),
                     //}));
@@ -109,7 +376,7 @@ impl Parser {
                     break;
                 }
                 Token::Error(err_msg) => {
-                    self.error_panic(format!("Tokenizer {}", err_msg));
+                    self.error_panic(err_msg.code, format!("Tokenizer {}", err_msg.message));
                 }
                 Token::EndOfFile() => {
                     break;
@@ -118,107 +385,403 @@ impl Parser {
         }
     }
 
-    fn token_to_ast_literal(tok: Token) -> ASTNodeLiteral {
+    fn token_to_ast_literal(&self, tok: Token) -> ASTNodeLiteral {
         match tok {
             Token::StringLiteral(ref str_lit) => return ASTNodeLiteral::Str(str_lit.clone()),
-            Token::NumberLiteral(number) => return ASTNodeLiteral::Float(number),
+            Token::NumberLiteral(TokenNumber {
+                value,
+                unit: Some(unit),
+                ..
+            }) => return ASTNodeLiteral::Measure { value, unit },
+            Token::NumberLiteral(number) => {
+                return ASTNodeLiteral::Float {
+                    value: number.value,
+                    raw: if self.preserve_number_text {
+                        Some(number.text)
+                    } else {
+                        None
+                    },
+                }
+            }
             Token::BoolLiteral(value) => return ASTNodeLiteral::Bool(value),
+            Token::Text(bareword) => return ASTNodeLiteral::Str(bareword.text),
             _ => panic!("The token was not a literal"),
         }
     }
 
-    fn parse_tag_block(&mut self, tag: &TokenTag) -> Option<ASTNodePtr> {
+    fn parse_tag_block<B: TreeBuilder>(
+        &mut self,
+        builder: &mut B,
+        tag: &TokenTag,
+    ) -> Option<B::Node> {
         let mut tag_node = ASTNodeTag::new(tag.text.clone());
-
+        let mut children = Vec::new();
+        tag_node.span = Span::at(
+            self.lexer.token_line_no,
+            self.lexer.token_column_no,
+            self.lexer.token_offset(),
+        );
         self.advance_token();
 
+        let open_paren_line_no = self.lexer.token_line_no;
+        let open_paren_column_no = self.lexer.token_column_no;
+        let mut is_attribute_list_unterminated = false;
+
+        self.lexer.push_mode(LexerMode::Code);
+        // NOTE(SR): Not `expect` as that accepts the end of the file, a tag may be the last thing in a document.
+        if self.current_token_is(&Token::OpenParen) {
+            self.advance_token();
+
+            while !self.current_token_is(&Token::CloseParen) {
+                // NOTE(SR): A brace can never be part of an attribute, assume the ')' was forgotten and the body (or parent's end) follows.
+                if self.current_token == Token::EndOfFile()
+                    || self.current_token_is(&Token::OpenBrace)
+                    || self.current_token_is(&Token::CloseBrace)
+                {
+                    self.error_at(
+                        ErrorCode::UnterminatedAttributeList,
+                        "Unterminated attribute list, expected ')'".to_string(),
+                        open_paren_line_no,
+                        open_paren_column_no,
+                    );
+                    is_attribute_list_unterminated = true;
+                    break;
+                }
+
+                let error_count = self.error_log.len();
+
+                self.parse_attribute(&mut tag_node);
+
+                if self.recover_from_errors && self.error_log.len() != error_count {
+                    self.skip_to_sequence_point();
+                }
+            }
+        }
+        // NOTE(SR): Leave Code mode before moving past the ')' so the text after it is lexed as text.
+        self.lexer.pop_mode();
+
+        if !is_attribute_list_unterminated && self.current_token_is(&Token::CloseParen) {
+            tag_node.span.end_line = self.lexer.token_line_no;
+            self.advance_token();
+        }
+
+        builder.open_tag(&tag_node);
+
+        // NOTE(SR): Tag Body is optional
+        if self.current_token_is(&Token::OpenBrace) {
+            let is_verbatim = self.verbatim_tags.contains(&tag.text);
+
+            // NOTE(SR): Switch modes before moving past the '{' / '}' so the token after each is lexed in the right mode.
+            if is_verbatim {
+                self.lexer.push_mode(LexerMode::Verbatim);
+            }
+
+            self.advance_token();
+
+            while !self.current_token_is(&Token::CloseBrace)
+                && self.current_token != Token::EndOfFile()
+            {
+                self.parse_impl(builder, &mut children);
+
+                if !self.current_token_is(&Token::CloseBrace) {
+                    self.skip_unexpected_token(&format!("'@{}'", tag.text));
+                }
+            }
+
+            if is_verbatim {
+                self.lexer.pop_mode();
+            }
+
+            tag_node.span.end_line = self.lexer.token_line_no;
+            self.expect(&Token::CloseBrace);
+        } else if self.implicit_body {
+            if let Token::Text(ref txt) = self.current_token {
+                if txt.line_no_start == tag_node.span.start_line {
+                    tag_node.span.end_line = txt.line_no_end_with_content;
+                    children.push(builder.text(Self::make_text_node(txt)));
+                    self.advance_token();
+                }
+            }
+        }
+
+        return Some(builder.tag(tag_node, children));
+    }
+
+    /// `<name attr=value ...>body</name>` or `<name attr=value ... />`.
+    fn parse_html_element<B: TreeBuilder>(&mut self, builder: &mut B, tag: &TokenTag) -> B::Node {
+        let mut tag_node = ASTNodeTag::new(tag.text.clone());
+        let mut children = Vec::new();
+        let mut is_self_closing = false;
+
+        tag_node.span = Span::at(
+            self.lexer.token_line_no,
+            self.lexer.token_column_no,
+            self.lexer.token_offset(),
+        );
         self.lexer.push_mode(LexerMode::Code);
-        if self.expect(&Token::Character('(')) {
-            // TODO(SR):
-            //   For better error messages I can skip until a ')' as that provides
-            //   a pretty good 'sequence point'.
+        self.advance_token();
 
-            while !self.expect(&Token::Character(')')) {
-                let variable_name = self.current_token.clone();
+        loop {
+            if self.current_token_is(&Token::Character('>')) {
+                break;
+            }
 
-                self.require(
-                    &Self::make_empty_token_text(),
-                    &format!("Variable must be a string name but got {}", variable_name),
+            if self.current_token_is(&Token::Character('/')) {
+                tag_node.span.end_line = self.lexer.token_line_no;
+                self.advance_token();
+                is_self_closing = true;
+                break;
+            }
+
+            // NOTE(SR): A ')', '{' or '}' cannot be an attribute and `parse_attribute` will not move past it,
+            //           so the tag ends here and the caller deals with the token.
+            if self.is_at_sequence_point() {
+                self.error_before_sequence_point(
+                    ErrorCode::UnterminatedHtmlTag,
+                    format!("Unterminated html tag '<{}'", tag.text),
                 );
+                self.lexer.pop_mode();
+                builder.open_tag(&tag_node);
+                return builder.tag(tag_node, children);
+            }
+
+            self.parse_attribute(&mut tag_node);
+        }
+
+        builder.open_tag(&tag_node);
+
+        // NOTE(SR): Leave Code mode before moving past the '>' so the body is lexed as text.
+        self.lexer.pop_mode();
+        tag_node.span.end_line = self.lexer.token_line_no;
+        self.expect(&Token::Character('>'));
+
+        if is_self_closing {
+            return builder.tag(tag_node, children);
+        }
 
-                self.require(
-                    &Token::Character('='),
-                    &format!("'{}' must be assigned to", variable_name),
+        loop {
+            self.parse_impl(builder, &mut children);
+
+            match self.current_token.clone() {
+                Token::HtmlCloseTag(close_tag) => {
+                    tag_node.span.end_line = self.lexer.token_line_no;
+
+                    if close_tag.text == tag.text {
+                        self.advance_token();
+                    } else {
+                        self.error_panic(
+                            ErrorCode::MismatchedClosingTag,
+                            format!("Expected '</{}>' but got '</{}>'", tag.text, close_tag.text),
+                        );
+                    }
+                    break;
+                }
+                Token::EndOfFile() => {
+                    self.error_panic(
+                        ErrorCode::MissingClosingTag,
+                        format!("Missing closing tag '</{}>'", tag.text),
+                    );
+                    break;
+                }
+                unexpected_token => {
+                    self.error_panic(
+                        ErrorCode::UnexpectedCharacter,
+                        format!("Unexpected {} inside of '<{}>'", unexpected_token, tag.text),
+                    );
+                }
+            }
+        }
+
+        return builder.tag(tag_node, children);
+    }
+
+    /// `name = literal` followed by an optional ',', shared by `@tag(...)` and `<tag ...>`.
+    fn parse_attribute(&mut self, tag_node: &mut ASTNodeTag) {
+        let variable_name = self.current_token.clone();
+        let variable_line_no = self.lexer.token_line_no;
+        let variable_column_no = self.lexer.token_column_no;
+        let variable_label = match &variable_name {
+            Token::Text(value) => format!("'{}'", value.text),
+            other => other.to_string(),
+        };
+
+        if !self.require(
+            &Self::make_empty_token_text(),
+            ErrorCode::ExpectedAttributeName,
+            &"attribute names must be text".to_string(),
+        ) {
+            return;
+        }
+
+        if !self.require(
+            &Token::Equals,
+            ErrorCode::ExpectedAssignment,
+            &format!("{} must be assigned to", variable_label),
+        ) {
+            return;
+        }
+
+        if let Some(literal) = self.parse_literal_value(&variable_label) {
+            let var_name_as_str = match variable_name {
+                Token::Text(value) if self.lowercase_attribute_names => value.text.to_lowercase(),
+                Token::Text(value) => value.text,
+                _ => panic!("The variable must be a text node"),
+            };
+
+            if tag_node.attributes.contains_key(&var_name_as_str) {
+                self.error_at(
+                    ErrorCode::DuplicateAttribute,
+                    format!("{} is assigned more than once", variable_label),
+                    variable_line_no,
+                    variable_column_no,
                 );
+            }
 
-                let literal_value = self.current_token.clone();
+            tag_node.attributes.insert(var_name_as_str, literal);
+        }
 
-                if literal_value.is_literal() {
-                    self.advance_token();
+        //
+        // NOTE(SR):
+        //   Commas are optional, since all literals
+        //   have a defined token there is no ambiguity.
+        //
+        self.expect(&Token::Character(','));
+    }
 
-                    let var_name_as_str = match variable_name {
-                        Token::Text(value) => value.text,
-                        _ => panic!("The variable must be a text node"),
-                    };
+    /// A single literal or a `[ ... ]` list of them (lists may nest and have a trailing comma).
+    fn parse_literal_value(&mut self, variable_label: &str) -> Option<ASTNodeLiteral> {
+        let literal_value = self.current_token.clone();
+        let is_bareword_value = self.bareword_values_as_strings
+            && self.current_token_is(&Self::make_empty_token_text());
 
-                    tag_node
-                        .attributes
-                        .insert(var_name_as_str, Parser::token_to_ast_literal(literal_value));
-                } else {
-                    self.error_panic(format!(
-                        "'{}' should have been a literal value",
-                        variable_name
-                    ));
+        if self.current_token_is(&Token::Character('[')) {
+            let open_bracket_line_no = self.lexer.token_line_no;
+            let open_bracket_column_no = self.lexer.token_column_no;
+            let mut items = Vec::new();
+
+            self.advance_token();
+
+            while !self.current_token_is(&Token::Character(']')) {
+                if self.current_token_is(&Token::EndOfFile())
+                    || self.current_token_is(&Token::CloseParen)
+                    || self.current_token_is(&Token::OpenBrace)
+                    || self.current_token_is(&Token::CloseBrace)
+                {
+                    self.error_at(
+                        ErrorCode::UnterminatedList,
+                        "Unterminated list, expected ']'".to_string(),
+                        open_bracket_line_no,
+                        open_bracket_column_no,
+                    );
+                    return Some(ASTNodeLiteral::List(items));
                 }
 
-                //
-                // NOTE(SR):
-                //   Commas are optional, since all literals
-                //   have a defined token there is no ambiguity.
-                //
+                items.push(self.parse_literal_value(variable_label)?);
+
+                // NOTE(SR): Same as attribute lists the commas are optional.
                 self.expect(&Token::Character(','));
             }
+
+            self.advance_token(); // ']'
+
+            return Some(ASTNodeLiteral::List(items));
         }
-        self.lexer.pop_mode();
 
-        // NOTE(SR): Tag Body is optional
-        if self.expect(&Token::Character('{')) {
-            while !self.expect(&Token::Character('}')) {
-                self.parse_impl(&mut tag_node.children);
-            }
+        if literal_value.is_literal() || is_bareword_value {
+            self.advance_token();
+            return Some(self.token_to_ast_literal(literal_value));
         }
 
-        return Some(Box::new(ASTNode::Tag(tag_node)));
+        if let Token::Error(err_msg) = literal_value {
+            self.error_panic(err_msg.code, format!("Tokenizer {}", err_msg.message));
+        } else {
+            let code = if literal_value == Token::EndOfFile() {
+                ErrorCode::UnexpectedEndOfFile
+            } else {
+                ErrorCode::ExpectedLiteral
+            };
+
+            self.error_before_sequence_point(
+                code,
+                format!(
+                    "{} should have been assigned a literal value but got {}",
+                    variable_label, literal_value
+                ),
+            );
+        }
+
+        return None;
+    }
+
+    /// ')', '{', '}' and the end of the file are where attribute lists and bodies end, so they are
+    /// never skipped over by error handling within an attribute (see `Parser::recover_from_errors`).
+    fn is_at_sequence_point(&self) -> bool {
+        return self.current_token_is(&Token::CloseParen)
+            || self.current_token_is(&Token::OpenBrace)
+            || self.current_token_is(&Token::CloseBrace)
+            || self.current_token == Token::EndOfFile();
+    }
+
+    fn skip_to_sequence_point(&mut self) {
+        while !self.is_at_sequence_point() {
+            self.advance_token();
+        }
+    }
+
+    /// Like `error_panic` but leaves a sequence point for the caller to handle.
+    fn error_before_sequence_point(&mut self, code: ErrorCode, message: String) {
+        if self.is_at_sequence_point() {
+            let line_number = self.lexer.token_line_no;
+            let column_number = self.lexer.token_column_no;
+
+            self.error_at(code, message, line_number, column_number);
+        } else {
+            self.error_panic(code, message);
+        }
+    }
+
+    /// `parse_impl` stops at tokens that cannot start a node (a stray '=' or '}'), report and move past
+    /// them so the rest of `container` is still parsed.
+    fn skip_unexpected_token(&mut self, container: &str) {
+        if self.current_token != Token::EndOfFile() {
+            self.error_panic(
+                ErrorCode::UnexpectedCharacter,
+                format!("Unexpected {} inside of {}", self.current_token, container),
+            );
+        }
     }
 
     fn current_token_is(&self, token: &Token) -> bool {
         let current_type = std::mem::discriminant(&self.current_token);
         let token_type = std::mem::discriminant(token);
 
-        if current_type == token_type {
-            if token_type != std::mem::discriminant(&Token::Character('_'))
-                || self.current_token == *token
-            {
-                return true;
-            }
+        if current_type == token_type
+            && (token_type != std::mem::discriminant(&Token::Character('_'))
+                || self.current_token == *token)
+        {
+            return true;
         }
 
         return false;
     }
 
-    fn require(&mut self, token: &Token, err_message: &String) -> bool {
+    fn require(&mut self, token: &Token, code: ErrorCode, err_message: &String) -> bool {
         if self.current_token_is(token) {
             self.advance_token();
             return true;
         }
 
-        self.error_panic(format!(
-            "Expected {} but got {}, {}",
-            *token, self.current_token, err_message
-        ));
+        self.error_before_sequence_point(
+            code,
+            format!(
+                "Expected {} but got {}, {}",
+                token.kind_name(),
+                self.current_token,
+                err_message
+            ),
+        );
 
-        // Prevent infinite loops by just returning true when at the end of a file.
-        return self.current_token == Token::EndOfFile();
+        return false;
     }
 
     fn expect(&mut self, token: &Token) -> bool {
@@ -235,21 +798,287 @@ impl Parser {
         self.current_token = self.lexer.get_next_token();
     }
 
-    fn error_panic(&mut self, message: String) {
+    /// Records an error at an earlier position without moving past the current token.
+    fn error_at(
+        &mut self,
+        code: ErrorCode,
+        message: String,
+        line_number: usize,
+        column_number: usize,
+    ) {
+        self.error_log.push(ParseError {
+            code,
+            message,
+            line_number,
+            column_number,
+        });
+    }
+
+    fn error_panic(&mut self, code: ErrorCode, message: String) {
+        // NOTE(SR): The position is of the offending token, so grab it before moving past it.
+        let line_number = self.lexer.token_line_no;
+        let column_number = self.lexer.token_column_no;
+
         // Advance the token as not to get stuck in infinite loops and better error messages.
         self.advance_token();
         self.error_log.push(ParseError {
-            message: message,
-            line_number: self.lexer.line_no,
+            code,
+            message,
+            line_number,
+            column_number,
         });
     }
 
+    fn make_text_node(txt: &TokenText) -> ASTNodeText {
+        let mut span = Span::at(txt.line_no_start, txt.column_no_start, txt.offset_start);
+        span.end_line = txt.line_no_end_with_content;
+
+        return ASTNodeText::new(txt.text.clone(), span);
+    }
+
     fn make_empty_token_text() -> Token {
         return Token::Text(TokenText {
             line_no_start: 0,
+            column_no_start: 0,
+            offset_start: 0,
             line_no_end_with_content: 0,
             line_no_end: 0,
             text: String::default(),
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_html(source: &str) -> ParseResult {
+        let mut parser = Parser::new(source.to_string());
+        parser.html_style_tags = true;
+
+        return parser.parse();
+    }
+
+    fn error_codes(result: ParseResult) -> Vec<ErrorCode> {
+        return match result {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.errors.iter().map(|error| error.code).collect(),
+        };
+    }
+
+    #[test]
+    fn html_tags_parse_like_srmark_tags() {
+        let html = parse_html("<p class=\"x\">hi</p>").ok().unwrap();
+        let srmark = parse("@p(class=\"x\"){hi}").ok().unwrap();
+
+        assert_eq!(html, srmark);
+    }
+
+    #[test]
+    fn html_tag_cut_short_by_a_paren_is_unterminated() {
+        assert_eq!(
+            error_codes(parse_html("<p ) >")),
+            [
+                ErrorCode::UnterminatedHtmlTag,
+                ErrorCode::UnexpectedCharacter
+            ]
+        );
+    }
+
+    #[test]
+    fn html_tag_cut_short_by_a_brace_is_unterminated() {
+        // NOTE(SR): The '}' and the then unopened '</p>' are reported as well.
+        assert_eq!(
+            error_codes(parse_html("<p }>x</p>")),
+            [
+                ErrorCode::UnterminatedHtmlTag,
+                ErrorCode::UnexpectedCharacter,
+                ErrorCode::UnexpectedCharacter,
+            ]
+        );
+    }
+
+    #[test]
+    fn error_messages_name_the_expected_token() {
+        let messages: Vec<String> = match parse("@t(x 5)") {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .errors
+                .iter()
+                .map(|error| error.message.clone())
+                .collect(),
+        };
+
+        assert_eq!(
+            messages,
+            ["Expected '=' but got number '5', 'x' must be assigned to"]
+        );
+    }
+
+    #[test]
+    fn every_error_code_is_reported() {
+        let cases = [
+            ("@t(x=\"a", ErrorCode::UnterminatedString),
+            ("/* open", ErrorCode::UnterminatedComment),
+            ("`code", ErrorCode::UnterminatedCodeSpan),
+            ("end\\", ErrorCode::UnterminatedEscape),
+            ("text \\u{ZZ}", ErrorCode::InvalidEscape),
+            ("@t(x=", ErrorCode::UnexpectedEndOfFile),
+            ("@t(x=1.2.3)", ErrorCode::MalformedNumber),
+            ("@-x", ErrorCode::InvalidTagName),
+            ("<p", ErrorCode::UnterminatedHtmlTag),
+            ("<p>x</q>", ErrorCode::MismatchedClosingTag),
+            ("<p>x", ErrorCode::MissingClosingTag),
+            ("@t(=1)", ErrorCode::ExpectedAttributeName),
+            ("@t(x 1)", ErrorCode::ExpectedAssignment),
+            ("@t(x=)", ErrorCode::ExpectedLiteral),
+            ("@t(x=1", ErrorCode::UnterminatedAttributeList),
+            ("@t(x=[1, 2)", ErrorCode::UnterminatedList),
+            ("@t(x=1 x=2)", ErrorCode::DuplicateAttribute),
+            ("@t{}}", ErrorCode::UnexpectedCharacter),
+        ];
+
+        for (source, code) in cases {
+            let mut parser = Parser::new(source.to_string());
+            parser.comments = true;
+            parser.inline_code_spans = true;
+            parser.html_style_tags = true;
+
+            assert_eq!(
+                error_codes(parser.parse()).first(),
+                Some(&code),
+                "'{}'",
+                source
+            );
+        }
+    }
+
+    fn tag(
+        name: &str,
+        attributes: &[(&str, ASTNodeLiteral)],
+        children: Vec<ASTNodePtr>,
+    ) -> ASTNodePtr {
+        let mut tag_node = ASTNodeTag::new(name.to_string());
+        tag_node.attributes = attributes
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+        tag_node.children = children;
+
+        return Box::new(ASTNode::Tag(tag_node));
+    }
+
+    fn text(text: &str) -> ASTNodePtr {
+        return Box::new(ASTNode::Text(ASTNodeText::new(
+            text.to_string(),
+            Span::default(),
+        )));
+    }
+
+    #[test]
+    fn parsed_tree_equals_a_hand_built_one() {
+        let expected = ASTNode::Root(ASTNodeRoot {
+            children: vec![
+                tag(
+                    "link",
+                    &[
+                        ("Src", ASTNodeLiteral::Str("a.html".to_string())),
+                        ("Hidden", ASTNodeLiteral::Bool(false)),
+                    ],
+                    vec![text("Read"), tag("b", &[], vec![text("more")])],
+                ),
+                text("after"),
+            ],
+            span: Span::default(),
+        });
+
+        assert_eq!(
+            *parse("@link(Src=\"a.html\" Hidden=false){Read @b{more}} after")
+                .ok()
+                .unwrap(),
+            expected
+        );
+        assert_ne!(
+            *parse("@link(Src=\"b.html\" Hidden=false){Read @b{more}} after")
+                .ok()
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn attribute_order_does_not_affect_equality() {
+        assert_eq!(
+            parse("@t(a=1 b=\"x\")").ok().unwrap(),
+            parse("@t(b=\"x\" a=1)").ok().unwrap()
+        );
+        assert_ne!(
+            parse("@t(a=1 b=\"x\")").ok().unwrap(),
+            parse("@t(a=1)").ok().unwrap()
+        );
+    }
+
+    fn parse_spaced_after(source: &str, keep_space_after_tag: bool) -> ASTNode {
+        let mut parser = Parser::new(source.to_string());
+        parser.keep_space_after_tag = keep_space_after_tag;
+
+        return *parser.parse().ok().unwrap();
+    }
+
+    fn root(children: Vec<ASTNodePtr>) -> ASTNode {
+        return ASTNode::Root(ASTNodeRoot {
+            children,
+            span: Span::default(),
+        });
+    }
+
+    #[test]
+    fn space_after_a_tag_is_kept_only_when_enabled() {
+        let source = "@p{@b{un} believable}";
+
+        assert_eq!(
+            parse_spaced_after(source, true),
+            root(vec![tag(
+                "p",
+                &[],
+                vec![tag("b", &[], vec![text("un")]), text(" believable")]
+            )])
+        );
+        assert_eq!(
+            parse_spaced_after(source, false),
+            root(vec![tag(
+                "p",
+                &[],
+                vec![tag("b", &[], vec![text("un")]), text("believable")]
+            )])
+        );
+    }
+
+    #[test]
+    fn text_directly_after_a_tag_gets_no_space() {
+        assert_eq!(
+            parse_spaced_after("@p{@b{un}believable}", true),
+            parse_spaced_after("@p{@b{un}believable}", false)
+        );
+    }
+
+    #[test]
+    fn space_between_two_tags_is_its_own_text_node() {
+        assert_eq!(
+            parse_spaced_after("@p{@b{one} @i{two}}", true),
+            root(vec![tag(
+                "p",
+                &[],
+                vec![
+                    tag("b", &[], vec![text("one")]),
+                    text(" "),
+                    tag("i", &[], vec![text("two")]),
+                ]
+            )])
+        );
+        assert_eq!(
+            parse_spaced_after("@p{@b{one} @i{two}}", false),
+            parse_spaced_after("@p{@b{one}@i{two}}", true)
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   query.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeTag;
+use crate::ast_events::events;
+use crate::ast_events::AstEvent;
+
+/// Gathers the value of attribute `key` from every tag in the tree, in document order.
+pub fn collect_attribute_values<'a>(root: &'a ASTNode, key: &str) -> Vec<&'a ASTNodeLiteral> {
+    let mut result = Vec::new();
+
+    for event in events(root) {
+        if let AstEvent::EnterTag(tag_node) = event {
+            if let Some(value) = tag_node.find_attribute(key) {
+                result.push(value);
+            }
+        }
+    }
+
+    return result;
+}
+
+/// Every tag in document order along with its path from the root, the tag's own name included
+/// (`["root", "article", "section", "p"]`).
+pub fn enumerate_tags_with_paths(root: &ASTNode) -> Vec<(Vec<String>, &ASTNodeTag)> {
+    let mut result = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+
+    for event in events(root) {
+        match event {
+            AstEvent::EnterRoot(_) => path.push("root".to_string()),
+            AstEvent::EnterTag(tag_node) => {
+                path.push(tag_node.text.clone());
+                result.push((path.clone(), tag_node));
+            }
+            AstEvent::ExitTag(_) | AstEvent::ExitRoot(_) => {
+                path.pop();
+            }
+            AstEvent::Text(_) | AstEvent::Literal(_) => {}
+        }
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn class_values_are_collected_in_document_order() {
+        let root = parse(
+            "@div(Class=\"page\"){ @p(Class=\"intro\"){A} @p{B} @ulist(Class=\"list\"){ @listitem(Class=\"item\"){C} } }",
+        )
+        .ok()
+        .unwrap();
+        let classes: Vec<String> = collect_attribute_values(&root, "Class")
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+
+        assert_eq!(classes, ["page", "intro", "list", "item"]);
+        assert!(collect_attribute_values(&root, "Missing").is_empty());
+    }
+
+    #[test]
+    fn deeply_nested_tags_have_their_full_path() {
+        let root = parse(
+            "@article{ @section{ @div{ @ulist{ @listitem{ @p(Id=\"deep\"){x} } } } } @aside{y} }",
+        )
+        .ok()
+        .unwrap();
+        let tags = enumerate_tags_with_paths(&root);
+        let (deep_path, deep_tag) = &tags[5];
+
+        assert_eq!(
+            deep_path,
+            &["root", "article", "section", "div", "ulist", "listitem", "p"]
+        );
+        assert_eq!(deep_tag.find_attribute_str("Id"), Some("deep"));
+        assert_eq!(tags[6].0, ["root", "article", "aside"]);
+        assert_eq!(tags.len(), 7);
+    }
+}
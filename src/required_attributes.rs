@@ -0,0 +1,94 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   required_attributes.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast_events::events;
+use crate::ast_events::AstEvent;
+
+/// Which attributes a tag must be given, tag and attribute names are matched ignoring case.
+#[derive(Debug, Clone, Default)]
+pub struct RequiredAttributes {
+    rules: Vec<(String, String)>, // (tag, attribute), checked in the order they were added.
+}
+
+/// A tag missing one of the attributes a `RequiredAttributes` rule asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingAttribute {
+    pub tag: String,
+    pub attribute: String,
+    pub line_number: usize,
+}
+
+impl RequiredAttributes {
+    /// No rules, every tag passes.
+    pub fn new() -> Self {
+        RequiredAttributes { rules: Vec::new() }
+    }
+
+    /// Rules for the tags whose output is broken without them:
+    /// `@image`, `@video` and `@link` need a `Src` and `@file` a `Source`.
+    pub fn srmark_defaults() -> Self {
+        let mut result = RequiredAttributes::new();
+        result
+            .require("image", "Src")
+            .require("video", "Src")
+            .require("link", "Src")
+            .require("file", "Source");
+        return result;
+    }
+
+    pub fn require(&mut self, tag: &str, attribute: &str) -> &mut Self {
+        self.rules.push((tag.to_string(), attribute.to_string()));
+        return self;
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        return self.rules.is_empty();
+    }
+}
+
+/// Lists every tag in `root` that is missing an attribute required by `rules`, in document order.
+///
+/// ```
+/// let root = srmarkup::parse("@text{ Logo: }\n@image(Alt=\"Logo\")").ok().unwrap();
+/// let missing = srmarkup::check_required_attributes(&root, &srmarkup::RequiredAttributes::srmark_defaults());
+///
+/// assert_eq!(
+///     missing,
+///     [srmarkup::MissingAttribute {
+///         tag: "image".to_string(),
+///         attribute: "Src".to_string(),
+///         line_number: 2,
+///     }]
+/// );
+/// ```
+pub fn check_required_attributes(
+    root: &ASTNode,
+    rules: &RequiredAttributes,
+) -> Vec<MissingAttribute> {
+    let mut missing = Vec::new();
+
+    if rules.is_empty() {
+        return missing;
+    }
+
+    for event in events(root) {
+        if let AstEvent::EnterTag(tag_node) = event {
+            for (tag, attribute) in &rules.rules {
+                if tag_node.text.eq_ignore_ascii_case(tag)
+                    && tag_node.find_attribute_ci(attribute).is_none()
+                {
+                    missing.push(MissingAttribute {
+                        tag: tag_node.text.clone(),
+                        attribute: attribute.clone(),
+                        line_number: tag_node.span.start_line,
+                    });
+                }
+            }
+        }
+    }
+
+    return missing;
+}
@@ -0,0 +1,75 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   semantic.rs
+//
+
+/// Output agnostic meaning of the common document tags.
+///
+/// Lets every output processor (HTML, Markdown, JSON, ...) agree that `@text`
+/// is a paragraph, `@link` is a hyperlink, etc. without each one keeping its own
+/// remapping table.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum SemanticTag {
+    Paragraph,
+    Heading(u8), // Level in the range [1, 6].
+    UnorderedList,
+    OrderedList,
+    ListItem,
+    Link,
+    Image,
+}
+
+/// Classifies a tag name (case insensitive) into its semantic kind.
+///
+/// Both the srmark spelling (`@text`, `@ulist`, `@link`) and the equivalent
+/// HTML spelling (`@p`, `@ul`, `@a`) are recognized.
+/// Returns `None` for tags with no special meaning.
+pub fn classify_tag(name: &str) -> Option<SemanticTag> {
+    let lower_case_name = name.to_lowercase();
+
+    return match lower_case_name.as_str() {
+        "text" | "p" => Some(SemanticTag::Paragraph),
+        "h1" => Some(SemanticTag::Heading(1)),
+        "h2" => Some(SemanticTag::Heading(2)),
+        "h3" => Some(SemanticTag::Heading(3)),
+        "h4" => Some(SemanticTag::Heading(4)),
+        "h5" => Some(SemanticTag::Heading(5)),
+        "h6" => Some(SemanticTag::Heading(6)),
+        "ulist" | "ul" => Some(SemanticTag::UnorderedList),
+        "olist" | "ol" => Some(SemanticTag::OrderedList),
+        "listitem" | "li" => Some(SemanticTag::ListItem),
+        "link" | "a" => Some(SemanticTag::Link),
+        "image" | "img" => Some(SemanticTag::Image),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srmark_tags_classify_like_their_html_spelling() {
+        assert_eq!(classify_tag("text"), Some(SemanticTag::Paragraph));
+        assert_eq!(classify_tag("ulist"), Some(SemanticTag::UnorderedList));
+        assert_eq!(classify_tag("link"), Some(SemanticTag::Link));
+
+        assert_eq!(classify_tag("text"), classify_tag("p"));
+        assert_eq!(classify_tag("ulist"), classify_tag("ul"));
+        assert_eq!(classify_tag("link"), classify_tag("a"));
+    }
+
+    #[test]
+    fn classification_ignores_case() {
+        assert_eq!(classify_tag("Text"), Some(SemanticTag::Paragraph));
+        assert_eq!(classify_tag("ULIST"), Some(SemanticTag::UnorderedList));
+        assert_eq!(classify_tag("H3"), Some(SemanticTag::Heading(3)));
+    }
+
+    #[test]
+    fn other_tags_have_no_meaning() {
+        assert_eq!(classify_tag("div"), None);
+        assert_eq!(classify_tag("h7"), None);
+        assert_eq!(classify_tag(""), None);
+    }
+}
@@ -0,0 +1,226 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   serialize.rs
+//
+
+use crate::ast::ASTNode;
+use crate::ast::ASTNodeList;
+use crate::ast::ASTNodeLiteral;
+use crate::ast::ASTNodeTag;
+
+/// Writes a tree back out as srmark source, parsing the result (with default `Parser` options,
+/// plus `measure_literals` if the tree has any) gives back an equal tree (see `ast_eq_ignoring_spans`).
+///
+/// Attributes keep the order they were written in but whitespace is normalized, so this is not a
+/// byte for byte copy of the original source. Text nodes next to each other (which only a parse with
+/// `Parser::comments` or `Parser::implicit_body` produces) are written separated by a space and so
+/// come back as one text node.
+///
+/// ```
+/// let root = srmarkup::parse("@t(c=3 a=1 b=2)").ok().unwrap();
+///
+/// assert_eq!(srmarkup::to_srmark_string(&root), "@t(c=3, a=1, b=2)");
+/// ```
+pub fn to_srmark_string(node: &ASTNode) -> String {
+    let mut result = String::new();
+
+    write_node(&mut result, node);
+
+    return result;
+}
+
+impl std::fmt::Display for ASTNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", to_srmark_string(self))
+    }
+}
+
+fn write_node(result: &mut String, node: &ASTNode) {
+    match node {
+        ASTNode::Root(root_node) => write_children(result, &root_node.children),
+        ASTNode::Tag(tag_node) => write_tag(result, tag_node),
+        ASTNode::Text(text_node) => write_text(result, &text_node.text),
+        ASTNode::Literal(literal_node) => write_literal(result, literal_node),
+    }
+}
+
+fn write_children(result: &mut String, children: &ASTNodeList) {
+    for (index, child) in children.iter().enumerate() {
+        if index != 0 {
+            result.push(' ');
+        }
+
+        write_node(result, child);
+    }
+}
+
+fn write_tag(result: &mut String, tag_node: &ASTNodeTag) {
+    result.push('@');
+
+    if is_plain_tag_name(&tag_node.text) {
+        result.push_str(&tag_node.text);
+    } else {
+        write_quoted_string(result, &tag_node.text);
+    }
+
+    if !tag_node.attributes.is_empty() {
+        result.push('(');
+
+        for (index, (key, value)) in tag_node.attributes.iter().enumerate() {
+            if index != 0 {
+                result.push_str(", ");
+            }
+
+            write_attribute_name(result, key);
+            result.push('=');
+            write_literal(result, value);
+        }
+
+        result.push(')');
+    }
+
+    if !tag_node.children.is_empty() {
+        result.push('{');
+        write_children(result, &tag_node.children);
+        result.push('}');
+    }
+}
+
+fn write_literal(result: &mut String, literal: &ASTNodeLiteral) {
+    match literal {
+        ASTNodeLiteral::Str(value) => write_quoted_string(result, value),
+        ASTNodeLiteral::List(items) => {
+            result.push('[');
+
+            for (index, item) in items.iter().enumerate() {
+                if index != 0 {
+                    result.push_str(", ");
+                }
+
+                write_literal(result, item);
+            }
+
+            result.push(']');
+        }
+        ASTNodeLiteral::Float { .. } | ASTNodeLiteral::Bool(_) | ASTNodeLiteral::Measure { .. } => {
+            result.push_str(&literal.to_string());
+        }
+    }
+}
+
+fn write_quoted_string(result: &mut String, value: &str) {
+    result.push('"');
+
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            result.push('\\');
+        }
+
+        result.push(c);
+    }
+
+    result.push('"');
+}
+
+/// Escapes everything the lexer would otherwise treat as the end of the text, a literal or a comment,
+/// along with the leading / trailing whitespace that would be trimmed.
+fn write_text(result: &mut String, text: &str) {
+    write_escaped(result, text, |c| matches!(c, '(' | ')'));
+}
+
+/// Attribute names are lexed in `LexerMode::Code` where whitespace and list punctuation also end text.
+fn write_attribute_name(result: &mut String, name: &str) {
+    write_escaped(result, name, |c| {
+        c.is_ascii_whitespace() || matches!(c, '(' | ')' | ',' | '[' | ']')
+    });
+}
+
+fn write_escaped(result: &mut String, text: &str, is_mode_special: impl Fn(char) -> bool) {
+    let starts_as_literal = text.starts_with(|c: char| c.is_ascii_digit())
+        || text.starts_with("true")
+        || text.starts_with("false");
+    let chars: Vec<char> = text.chars().collect();
+
+    for (index, c) in chars.iter().copied().enumerate() {
+        let next_char = chars.get(index + 1).copied().unwrap_or('\0');
+        let is_edge = index == 0 || index + 1 == chars.len();
+        let needs_escape = matches!(c, '@' | '{' | '}' | '=' | '"' | '\\' | '\n' | '\r' | '\t')
+            || is_mode_special(c)
+            || (c == '/' && (next_char == '/' || next_char == '*'))
+            || (is_edge && c.is_whitespace())
+            || (index == 0 && starts_as_literal);
+
+        if needs_escape {
+            write_escaped_char(result, c);
+        } else {
+            result.push(c);
+        }
+    }
+}
+
+fn write_escaped_char(result: &mut String, c: char) {
+    match c {
+        '\n' => result.push_str("\\n"),
+        '\r' => result.push_str("\\r"),
+        '\t' => result.push_str("\\t"),
+        // NOTE(SR): `\n`, `\r` and `\t` mean something else, spell these out as codepoints.
+        'n' | 'r' | 't' => result.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        _ => {
+            result.push('\\');
+            result.push(c);
+        }
+    }
+}
+
+/// Names the lexer reads back as is after an '@', anything else is written quoted (`@"two words"`).
+fn is_plain_tag_name(name: &str) -> bool {
+    return !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::ASTNodeRoot;
+    use crate::ast::ASTNodeText;
+    use crate::ast::Span;
+    use crate::parser::parse;
+
+    #[test]
+    fn parsing_the_output_gives_back_the_tree() {
+        let source = "Intro @article(Title=\"A \\\"quoted\\\" title\" Tags=[\"a\", \"b\"] Draft=false Order=2.5){\n\
+                      @h1{Heading} Some \\@escaped \\{text\\} then @b{bold}, 42 and \"a string\".\n\
+                      @\"odd name\"{x} @br @ulist{ @listitem{one} @listitem{two} } } Outro";
+        let ast = parse(source).ok().unwrap();
+        let written = to_srmark_string(&ast);
+
+        assert_eq!(parse(&written).ok().unwrap(), ast, "{}", written);
+    }
+
+    #[test]
+    fn adjacent_text_nodes_come_back_joined() {
+        let text = |text: &str| {
+            Box::new(ASTNode::Text(ASTNodeText::new(
+                text.to_string(),
+                Span::default(),
+            )))
+        };
+        let root = ASTNode::Root(ASTNodeRoot {
+            children: vec![text("one"), text("two")],
+            span: Span::default(),
+        });
+        let written = to_srmark_string(&root);
+
+        assert_eq!(written, "one two");
+        assert_eq!(
+            *parse(&written).ok().unwrap(),
+            ASTNode::Root(ASTNodeRoot {
+                children: vec![text("one two")],
+                span: Span::default(),
+            })
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   ast_dump_stdin.rs
+//
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn dump(args: &[&str], source: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sr-markup-ast-dump"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // NOTE(SR): Dropping stdin closes it so the dumper sees the end of the document.
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+
+    return String::from_utf8(output.stdout).unwrap();
+}
+
+#[test]
+fn document_is_read_from_stdin_without_an_input() {
+    let expected = concat!(
+        "{\"type\":\"root\",\"children\":[\n",
+        "  {\"type\":\"tag\",\"name\":\"p\",\"attributes\":{\"Id\":\"x\"},\"children\":[\n",
+        "    {\"type\":\"text\",\"text\":\"Hello\"},\n",
+        "    {\"type\":\"tag\",\"name\":\"b\",\"attributes\":{},\"children\":[\n",
+        "      {\"type\":\"text\",\"text\":\"world\"}\n",
+        "    ]}\n",
+        "  ]}\n",
+        "]}\n",
+    );
+
+    assert_eq!(
+        dump(&["--format", "json"], "@p(Id=\"x\"){Hello @b{world}}"),
+        expected
+    );
+}
+
+#[test]
+fn dash_input_reads_stdin_as_well() {
+    let source = "@p{Hello @b{world}}";
+
+    assert_eq!(dump(&["--input", "-"], source), dump(&[], source));
+    assert!(dump(&["--input", "-"], source).starts_with("SR-Markup Dump\n"));
+}
@@ -0,0 +1,44 @@
+//
+// Author: Shareef Abdoul-Raheem
+// File:   public_api.rs
+//
+
+// NOTE(SR): Only items re-exported from the crate root are used here, `srmarkup::lexer::*` etc. are off limits.
+
+use srmarkup::Lexer;
+use srmarkup::LexerMode;
+use srmarkup::Token;
+use srmarkup::TokenTag;
+use srmarkup::TokenText;
+
+#[test]
+fn lexer_is_usable_from_the_crate_root() {
+    let mut lexer = Lexer::new("@link(Src=\"a.html\"){Read more}".to_string());
+    let mut tag_names = Vec::new();
+    let mut texts = Vec::new();
+
+    loop {
+        match lexer.get_next_token() {
+            Token::Tag(TokenTag { text, .. }) => tag_names.push(text),
+            Token::Text(TokenText { text, .. }) => texts.push(text),
+            Token::OpenParen => lexer.push_mode(LexerMode::Code),
+            Token::CloseParen => lexer.pop_mode(),
+            Token::EndOfFile() => break,
+            _ => {}
+        }
+    }
+
+    assert_eq!(tag_names, ["link"]);
+    assert_eq!(texts, ["Src", "Read more"]);
+}
+
+#[test]
+fn documents_parse_from_the_crate_root() {
+    let root = srmarkup::parse("@p{Hello @b{world}}").ok().unwrap();
+    let mut tag_names = Vec::new();
+
+    srmarkup::visit_ast_with(&root, |tag| tag_names.push(tag.text.clone()), |_| {});
+
+    assert_eq!(tag_names, ["p", "b"]);
+    assert_eq!(srmarkup::check_brace_balance("@p{"), Err((1, 3)));
+}